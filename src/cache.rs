@@ -0,0 +1,225 @@
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tokio::io::AsyncReadExt;
+use crate::models::TranscriptRecord;
+
+/// Local store of finished transcriptions, keyed by audio content and parameters.
+///
+/// Identical recordings under different names share one entry, so they are
+/// only uploaded once.
+#[derive(Debug, Clone)]
+pub struct ResultCache {
+    dir: PathBuf,
+}
+
+/// Summary of what is in the cache.
+#[derive(Debug, Clone, Default)]
+pub struct CacheStats {
+    pub entries: usize,
+    pub total_bytes: u64,
+    pub oldest: Option<SystemTime>,
+    pub newest: Option<SystemTime>,
+}
+
+impl ResultCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// The platform cache directory, e.g. `~/.cache/whisper-client` on Linux.
+    pub fn default_dir() -> Option<PathBuf> {
+        crate::paths::cache_dir()
+    }
+
+    pub fn open_default() -> Result<Self> {
+        let dir = Self::default_dir().context("Could not determine cache directory")?;
+        Ok(Self::new(dir))
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Cache key for `path`: the SHA-256 of its bytes followed by `params`.
+    pub async fn key_for(path: &Path, params: &str) -> std::io::Result<String> {
+        let mut file = tokio::fs::File::open(path).await?;
+        let mut hasher = Sha256::new();
+        let mut buffer = vec![0; 64 * 1024];
+        loop {
+            let read = file.read(&mut buffer).await?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+        }
+        hasher.update([0]);
+        hasher.update(params.as_bytes());
+
+        Ok(hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect())
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+
+    /// The cached transcription for `key`, if there is a readable one.
+    pub fn get(&self, key: &str) -> Option<TranscriptRecord> {
+        let content = std::fs::read_to_string(self.entry_path(key)).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    pub fn put(&self, key: &str, record: &TranscriptRecord) -> Result<()> {
+        std::fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create cache directory {}", self.dir.display()))?;
+        let path = self.entry_path(key);
+        std::fs::write(&path, serde_json::to_string(record)?)
+            .with_context(|| format!("Failed to write cache entry {}", path.display()))?;
+        Ok(())
+    }
+
+    /// Cache entry files with their metadata.
+    fn entries(&self) -> Result<Vec<(PathBuf, std::fs::Metadata)>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut entries = Vec::new();
+        for entry in std::fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                let metadata = std::fs::metadata(&path)?;
+                entries.push((path, metadata));
+            }
+        }
+        Ok(entries)
+    }
+
+    pub fn stats(&self) -> Result<CacheStats> {
+        let mut stats = CacheStats::default();
+        for (_, metadata) in self.entries()? {
+            stats.entries += 1;
+            stats.total_bytes += metadata.len();
+            if let Ok(modified) = metadata.modified() {
+                stats.oldest = Some(stats.oldest.map_or(modified, |t| t.min(modified)));
+                stats.newest = Some(stats.newest.map_or(modified, |t| t.max(modified)));
+            }
+        }
+        Ok(stats)
+    }
+
+    /// Remove every entry, returning how many were removed.
+    pub fn clear(&self) -> Result<usize> {
+        self.remove_where(|_| true)
+    }
+
+    /// Remove entries last written more than `age` ago, returning how many were removed.
+    pub fn prune(&self, age: Duration) -> Result<usize> {
+        let cutoff = SystemTime::now().checked_sub(age).unwrap_or(SystemTime::UNIX_EPOCH);
+        self.remove_where(|metadata| metadata.modified().is_ok_and(|modified| modified < cutoff))
+    }
+
+    fn remove_where(&self, predicate: impl Fn(&std::fs::Metadata) -> bool) -> Result<usize> {
+        let mut removed = 0;
+        for (path, metadata) in self.entries()? {
+            if predicate(&metadata) {
+                std::fs::remove_file(&path)
+                    .with_context(|| format!("Failed to remove cache entry {}", path.display()))?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+}
+
+/// Parse a duration such as `30d`, `12h`, `45m` or `90s`. A bare number means days.
+pub fn parse_age(value: &str) -> Result<Duration> {
+    let value = value.trim();
+    let split = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: u64 = number
+        .parse()
+        .with_context(|| format!("Invalid duration '{}'", value))?;
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "" | "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => anyhow::bail!("Invalid duration unit '{}' (use s, m, h, d or w)", unit),
+    };
+    Ok(Duration::from_secs(number * seconds))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{JobResponse, JobStatus, TranscriptionResponse};
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_key_depends_on_content_and_params() {
+        let temp_dir = tempdir().unwrap();
+        let a = temp_dir.path().join("a.mp3");
+        let b = temp_dir.path().join("renamed copy.mp3");
+        let c = temp_dir.path().join("c.mp3");
+        std::fs::write(&a, "same audio").unwrap();
+        std::fs::write(&b, "same audio").unwrap();
+        std::fs::write(&c, "other audio").unwrap();
+
+        let key_a = ResultCache::key_for(&a, "").await.unwrap();
+        assert_eq!(key_a.len(), 64);
+        assert_eq!(key_a, ResultCache::key_for(&b, "").await.unwrap());
+        assert_ne!(key_a, ResultCache::key_for(&c, "").await.unwrap());
+        assert_ne!(key_a, ResultCache::key_for(&a, "language=de").await.unwrap());
+    }
+
+    #[test]
+    fn test_put_get_and_manage() {
+        let temp_dir = tempdir().unwrap();
+        let cache = ResultCache::new(temp_dir.path().join("cache"));
+        assert_eq!(cache.stats().unwrap().entries, 0);
+        assert!(cache.get("missing").is_none());
+
+        let transcription: TranscriptionResponse =
+            serde_json::from_str(include_str!("../tests/fixtures/test.json")).unwrap();
+        let job = JobResponse {
+            job_id: String::from("job-1"),
+            status: JobStatus::Completed,
+            message: String::new(),
+            result: None,
+            file_info: None,
+            created_at: None,
+            filename: None,
+            progress: None,
+        };
+        cache.put("abc", &TranscriptRecord::new(transcription, &job)).unwrap();
+        cache.put("def", &TranscriptRecord::new(TranscriptionResponse {
+            text: String::new(),
+            segments: Vec::new(),
+            extra: Default::default(),
+        }, &job)).unwrap();
+
+        let record = cache.get("abc").unwrap();
+        assert_eq!(record.job.job_id, "job-1");
+        assert_eq!(record.transcription.segments.len(), 2);
+
+        let stats = cache.stats().unwrap();
+        assert_eq!(stats.entries, 2);
+        assert!(stats.total_bytes > 0);
+
+        assert_eq!(cache.prune(Duration::from_secs(3600)).unwrap(), 0);
+        assert_eq!(cache.prune(Duration::ZERO).unwrap(), 2);
+        assert_eq!(cache.clear().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_parse_age() {
+        assert_eq!(parse_age("90s").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_age("12h").unwrap(), Duration::from_secs(12 * 3600));
+        assert_eq!(parse_age("30d").unwrap(), Duration::from_secs(30 * 86400));
+        assert_eq!(parse_age("7").unwrap(), Duration::from_secs(7 * 86400));
+        assert!(parse_age("soon").is_err());
+        assert!(parse_age("5y").is_err());
+    }
+}
@@ -1,332 +1,893 @@
-use anyhow::{Context, Result};
-use reqwest::multipart;
-use std::path::PathBuf;
+use futures_util::future::join_all;
+use futures_util::TryStreamExt;
+use reqwest::header::HeaderMap;
+use reqwest::{multipart, StatusCode};
+use serde::de::DeserializeOwned;
+use std::path::Path;
+use std::sync::Arc;
 use std::time::Duration;
-use crate::models::{JobResponse, TranscriptionResponse};
+use crate::auth::{redact_url_password, Redactor};
+use crate::cache::ResultCache;
+use crate::endpoints::{least_busy_first, EndpointLoad, Endpoints};
+use crate::events::{EventStream, JobEvent};
+use crate::models::{JobResponse, JobStatus, TranscriptRecord, TranscriptionOptions, TranscriptionResponse};
 use crate::config::Config;
-use indicatif::{ProgressBar, ProgressStyle};
+use crate::error::{Result, WhisperError};
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 use colored::*;
+use tokio_util::io::ReaderStream;
 
-lazy_static::lazy_static! {
-    static ref CONFIG: Config = Config::load().expect("Failed to load config");
+/// A client for a whisper-service instance, or for several listed in the config's
+/// `endpoints`. Uploads then go to the least busy instance, and everything else about
+/// a job goes to the instance that owns it.
+///
+/// Cloning is cheap: clones share the underlying connection pool.
+#[derive(Debug, Clone)]
+pub struct WhisperClient {
+    http: reqwest::Client,
+    config: Config,
+    cache: Option<ResultCache>,
+    /// Credential headers sent with every request.
+    auth_headers: HeaderMap,
+    redactor: Redactor,
+    endpoints: Arc<Endpoints>,
 }
 
-pub async fn check_service() -> Result<()> {
-    let client = reqwest::Client::new();
-    let response = client
-        .get(&format!("{}/health", CONFIG.service_url))
-        .timeout(Duration::from_secs(5))
-        .send()
-        .await
-        .context("Failed to connect to Whisper service")?;
+/// Polling starts at the shortest interval and backs off while the job doesn't change.
+const MIN_POLL_INTERVAL: Duration = Duration::from_secs(1);
+const MAX_POLL_INTERVAL: Duration = Duration::from_secs(10);
 
-    let status = response.status();
-    if !status.is_success() {
-        let error_text = response.text().await.unwrap_or_default();
-        anyhow::bail!("Service health check failed with status {}: {}", status, error_text);
-    }
+/// Failed polls in a row after which a job's instance counts as down, if there are
+/// other instances to fail over to.
+const MAX_UNAVAILABLE_POLLS: u32 = 3;
 
-    Ok(())
+/// Where [`WhisperClient::wait_for_job_with_progress`] gets job updates from.
+enum StatusSource {
+    /// Nothing asked for yet.
+    Unopened,
+    Events(EventStream),
+    Polling {
+        interval: Duration,
+        /// Status and progress at the last poll, to tell whether the job is moving.
+        last: Option<(JobStatus, Option<u64>)>,
+    },
 }
 
-pub async fn get_job_status(job_id: &str, include_transcript: bool) -> Result<JobResponse> {
-    let client = reqwest::Client::new();
-    let response = client
-        .get(&format!("{}/status/{}?include_transcript={}", 
-            CONFIG.service_url, 
-            job_id,
-            include_transcript))
-        .send()
-        .await
-        .context("Failed to get job status")?;
+/// Callbacks from [`WhisperClient::transcribe_file_with`], e.g. to keep a record of the
+/// jobs a file was submitted as. `()` ignores them all.
+pub trait TranscribeHooks: Sync {
+    /// The result came from the cache, so nothing was uploaded.
+    fn cached(&self) {}
 
-    if !response.status().is_success() {
-        anyhow::bail!(
-            "Service error: {}",
-            response.text().await.unwrap_or_default()
-        );
+    /// The file was uploaded as `job`. Called again if it has to be submitted again.
+    fn submitted(&self, _job: &JobResponse) {}
+}
+
+impl TranscribeHooks for () {}
+
+/// Builder for [`WhisperClient`].
+#[derive(Debug, Default)]
+pub struct WhisperClientBuilder {
+    config: Option<Config>,
+    service_url: Option<String>,
+    http: Option<reqwest::Client>,
+    cache: Option<ResultCache>,
+}
+
+impl WhisperClientBuilder {
+    /// Start from an existing configuration instead of the defaults.
+    pub fn config(mut self, config: Config) -> Self {
+        self.config = Some(config);
+        self
     }
 
-    let job_status: JobResponse = response
-        .json()
-        .await
-        .context("Failed to parse job status response")?;
+    /// Override the service URL.
+    pub fn service_url(mut self, url: impl Into<String>) -> Self {
+        self.service_url = Some(url.into());
+        self
+    }
 
-    Ok(job_status)
+    /// Use a preconfigured `reqwest::Client`, e.g. to share a pool between clients.
+    ///
+    /// The config's connect timeout and TLS settings are not applied to it.
+    pub fn http_client(mut self, http: reqwest::Client) -> Self {
+        self.http = Some(http);
+        self
+    }
+
+    /// Reuse finished transcriptions of identical audio from `cache` instead of uploading.
+    pub fn cache(mut self, cache: ResultCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    pub fn build(self) -> Result<WhisperClient> {
+        let mut config = self.config.unwrap_or_default();
+        if let Some(url) = self.service_url {
+            config.service_url = url;
+        }
+
+        let http = match self.http {
+            Some(http) => http,
+            None => {
+                let builder = reqwest::Client::builder().connect_timeout(config.timeouts.connect());
+                let builder = config.proxy.apply(config.tls.apply(builder)?)?;
+                builder.build()
+            }
+                .map_err(|e| WhisperError::Config(format!("Failed to build HTTP client: {}", e)))?,
+        };
+
+        let (auth_headers, redactor) = config.auth.resolve()?;
+        let endpoints = Endpoints::new(&config.service_url, &config.endpoints);
+
+        Ok(WhisperClient {
+            http,
+            config,
+            cache: self.cache,
+            auth_headers,
+            redactor,
+            endpoints: Arc::new(endpoints),
+        })
+    }
 }
 
-pub async fn list_jobs() -> Result<Vec<JobResponse>> {
-    let client = reqwest::Client::new();
-    let response = client
-        .get(&format!("{}/jobs", CONFIG.service_url))
-        .send()
-        .await
-        .context("Failed to list jobs")?;
+impl WhisperClient {
+    pub fn new(config: Config) -> Result<Self> {
+        Self::builder().config(config).build()
+    }
 
-    if !response.status().is_success() {
-        anyhow::bail!(
-            "Service error: {}",
-            response.text().await.unwrap_or_default()
-        );
+    pub fn builder() -> WhisperClientBuilder {
+        WhisperClientBuilder::default()
+    }
+
+    /// Build a client from the user's config file, with the selected profile and any
+    /// `WHISPER_CLIENT_*` environment overrides applied.
+    pub fn from_config_file() -> Result<Self> {
+        let config = Config::load().map_err(|e| WhisperError::Config(format!("{:#}", e)))?;
+        Self::new(config.with_profile_and_overrides(None, &Config::env_overrides())?)
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn service_url(&self) -> &str {
+        self.endpoints.url(0)
+    }
+
+    /// Every service instance, starting with [`service_url`](Self::service_url).
+    pub fn endpoints(&self) -> &[String] {
+        self.endpoints.urls()
+    }
+
+    /// The instance `job_id` was found on or submitted to, if known yet.
+    pub fn job_endpoint(&self, job_id: &str) -> Option<&str> {
+        self.endpoints.owner(job_id).map(|index| self.endpoints.url(index))
+    }
+
+    /// Whether a job that failed with `error` is worth submitting again, to another instance.
+    pub fn can_fail_over(&self, error: &WhisperError) -> bool {
+        self.endpoints.len() > 1 && error.is_unavailable()
+    }
+
+    pub fn cache(&self) -> Option<&ResultCache> {
+        self.cache.as_ref()
+    }
+
+    /// The cached result of transcribing `path` with `options`, if there is one.
+    pub async fn cached_result(&self, path: &Path, options: &TranscriptionOptions) -> Result<Option<TranscriptRecord>> {
+        let key = self.cache_key(path, options).await?;
+        Ok(self.cache.as_ref().zip(key).and_then(|(cache, key)| cache.get(&key)))
+    }
+
+    /// The cache key for transcribing `path` with `options`, or `None` without a cache.
+    async fn cache_key(&self, path: &Path, options: &TranscriptionOptions) -> Result<Option<String>> {
+        if self.cache.is_none() {
+            return Ok(None);
+        }
+        let params = options
+            .form_fields()
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let key = ResultCache::key_for(path, &params)
+            .await
+            .map_err(|e| WhisperError::io(path, e))?;
+        Ok(Some(key))
+    }
+
+    fn url(&self, endpoint: usize, path: &str) -> String {
+        format!("{}{}", self.endpoints.url(endpoint), path)
+    }
+
+    /// Send a request, retrying transient failures as the config's retry policy allows.
+    ///
+    /// Requests with a streaming body can't be repeated, so they are sent once.
+    async fn execute(&self, request: reqwest::RequestBuilder) -> Result<reqwest::Response> {
+        if request.try_clone().is_none() {
+            return self.execute_once(request).await;
+        }
+        self.config
+            .retry
+            .run(true, || self.execute_once(request.try_clone().expect("request was cloneable above")))
+            .await
+    }
+
+    /// Send a request once, turning transport failures and non-success statuses into errors.
+    ///
+    /// Requests without a timeout of their own get the configured request timeout.
+    async fn execute_once(&self, request: reqwest::RequestBuilder) -> Result<reqwest::Response> {
+        let mut request = request
+            .headers(self.auth_headers.clone())
+            .build()
+            .map_err(|e| WhisperError::Config(format!("Invalid request: {}", e)))?;
+        request.timeout_mut().get_or_insert(self.config.timeouts.request());
+        let url = redact_url_password(request.url().as_str());
+        // The proxy in the way, to blame it for connection failures
+        let proxy = self.config.proxy.proxy_for(request.url().as_str());
+
+        let response = self.http
+            .execute(request)
+            .await
+            .map_err(|e| match &proxy {
+                Some(proxy) if e.is_connect() => WhisperError::Proxy {
+                    proxy: proxy.clone(),
+                    message: error_chain(&e.without_url()),
+                },
+                _ => WhisperError::from_transport(&url, e.without_url()),
+            })?;
+
+        let status = response.status();
+        if let (Some(proxy), StatusCode::PROXY_AUTHENTICATION_REQUIRED) = (&proxy, status) {
+            return Err(WhisperError::Proxy {
+                proxy: proxy.clone(),
+                message: "proxy authentication required (407)".to_string(),
+            });
+        }
+        if !status.is_success() {
+            // Proxies sometimes echo the request back; keep credentials out of the error
+            let body = self.redactor.redact(&response.text().await.unwrap_or_default());
+            return Err(WhisperError::Http { status, body });
+        }
+
+        Ok(response)
     }
 
-    // Get the response text to handle malformed JSON
-    let text = response.text().await.context("Failed to get response text")?;
-    
-    // Fix malformed JSON if needed
-    let fixed_text = if !text.trim().starts_with('{') {
-        format!("{{{}", text)
-    } else {
-        text
-    };
-    
-    // Try to parse the fixed JSON
-    match serde_json::from_str::<serde_json::Value>(&fixed_text) {
-        Ok(value) => {
-            if let Some(jobs_array) = value.get("jobs").and_then(|j| j.as_array()) {
-                let jobs: Vec<JobResponse> = serde_json::from_value(jobs_array.clone().into())
-                    .context("Failed to parse jobs array")?;
-                Ok(jobs)
-            } else {
-                // If there's no "jobs" field, try to parse as a direct array
-                let jobs: Vec<JobResponse> = serde_json::from_str(&fixed_text)
-                    .context("Failed to parse as direct jobs array")?;
-                Ok(jobs)
+    /// Send a request about `job_id` to the instance that owns it. While that isn't known,
+    /// every instance is asked in turn until one has the job.
+    async fn execute_for_job(
+        &self,
+        job_id: &str,
+        request: impl Fn(&str) -> reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response> {
+        if let Some(endpoint) = self.endpoints.owner(job_id) {
+            return self.execute(request(self.endpoints.url(endpoint))).await.map_err(|e| e.for_job(job_id));
+        }
+
+        let mut error = None;
+        for endpoint in 0..self.endpoints.len() {
+            match self.execute(request(self.endpoints.url(endpoint))).await.map_err(|e| e.for_job(job_id)) {
+                Ok(response) => {
+                    self.endpoints.set_owner(job_id, endpoint);
+                    return Ok(response);
+                }
+                // Only report "not found" if no instance had anything else to say
+                Err(WhisperError::JobNotFound(_)) if error.is_some() => {}
+                Err(e) => error = Some(e),
             }
-        },
-        Err(_) => {
-            // Return empty list as fallback
-            Ok(Vec::new())
         }
+        Err(error.expect("there is always at least one endpoint"))
     }
-}
 
-pub async fn terminate_job(job_id: &str) -> Result<JobResponse> {
-    let client = reqwest::Client::new();
-    let response = client
-        .delete(&format!("{}/jobs/{}", CONFIG.service_url, job_id))
-        .send()
-        .await
-        .context("Failed to terminate job")?;
+    /// Check that the service is up. With several instances, any one of them will do.
+    pub async fn check_service(&self) -> Result<()> {
+        let mut error = None;
+        for endpoint in 0..self.endpoints.len() {
+            match self.check_endpoint(endpoint).await {
+                Ok(()) => return Ok(()),
+                Err(e) => {
+                    error.get_or_insert(e);
+                }
+            }
+        }
+        Err(error.expect("there is always at least one endpoint"))
+    }
 
-    if !response.status().is_success() {
-        anyhow::bail!(
-            "Service error: {}",
-            response.text().await.unwrap_or_default()
-        );
+    async fn check_endpoint(&self, endpoint: usize) -> Result<()> {
+        self.execute(self.http.get(self.url(endpoint, "/health"))).await?;
+
+        Ok(())
     }
 
-    let job_status: JobResponse = response
-        .json()
+    /// Health and queue depth of every instance.
+    pub async fn endpoint_loads(&self) -> Vec<EndpointLoad> {
+        join_all((0..self.endpoints.len()).map(|endpoint| async move {
+            let active_jobs = match self.check_endpoint(endpoint).await {
+                Ok(()) => self
+                    .list_jobs_at(endpoint)
+                    .await
+                    .map(|jobs| jobs.iter().filter(|job| job.status.is_active()).count()),
+                Err(e) => Err(e),
+            };
+            EndpointLoad { url: self.endpoints.url(endpoint).to_string(), active_jobs }
+        }))
         .await
-        .context("Failed to parse job status response")?;
+    }
 
-    Ok(job_status)
-}
+    pub async fn get_job_status(&self, job_id: &str, include_transcript: bool) -> Result<JobResponse> {
+        let response = self
+            .execute_for_job(job_id, |base| {
+                self.http.get(format!("{}/status/{}?include_transcript={}", base, job_id, include_transcript))
+            })
+            .await?;
+
+        parse_json(response).await
+    }
 
-// Helper function to check if a job exists on the server
-async fn check_job_exists(job_id: &str) -> Result<bool> {
-    match get_job_status(job_id, false).await {
-        Ok(_) => Ok(true),
-        Err(e) => {
-            // Check if the error is due to job not found (404)
-            if e.to_string().contains("404") || e.to_string().contains("not found") {
-                Ok(false)
-            } else {
-                // For other errors, propagate them
-                Err(e)
+    /// Jobs on every instance. Instances that can't be reached are left out, unless
+    /// none can.
+    pub async fn list_jobs(&self) -> Result<Vec<JobResponse>> {
+        let results = join_all((0..self.endpoints.len()).map(|endpoint| self.list_jobs_at(endpoint))).await;
+        let mut jobs = Vec::new();
+        let mut error = None;
+        let mut any_listed = false;
+        for result in results {
+            match result {
+                Ok(listed) => {
+                    any_listed = true;
+                    jobs.extend(listed);
+                }
+                Err(e) => {
+                    error.get_or_insert(e);
+                }
             }
         }
+        match error {
+            Some(e) if !any_listed => Err(e),
+            _ => Ok(jobs),
+        }
     }
-}
 
-pub async fn transcribe_file(path: &PathBuf) -> Result<(TranscriptionResponse, JobResponse)> {
-    if !path.exists() {
-        anyhow::bail!("File does not exist: {}", path.display());
+    async fn list_jobs_at(&self, endpoint: usize) -> Result<Vec<JobResponse>> {
+        let response = self.execute(self.http.get(self.url(endpoint, "/jobs"))).await?;
+
+        // Get the response text to handle malformed JSON
+        let text = response
+            .text()
+            .await
+            .map_err(|e| WhisperError::MalformedResponse(e.to_string()))?;
+
+        // Fix malformed JSON if needed
+        let fixed_text = if !text.trim().starts_with('{') {
+            format!("{{{}", text)
+        } else {
+            text
+        };
+
+        // Try to parse the fixed JSON
+        match serde_json::from_str::<serde_json::Value>(&fixed_text) {
+            Ok(value) => {
+                if let Some(jobs_array) = value.get("jobs").and_then(|j| j.as_array()) {
+                    let jobs: Vec<JobResponse> = serde_json::from_value(jobs_array.clone().into())
+                        .map_err(|e| WhisperError::MalformedResponse(format!("Failed to parse jobs array: {}", e)))?;
+                    Ok(self.owned_by(endpoint, jobs))
+                } else {
+                    // If there's no "jobs" field, try to parse as a direct array
+                    let jobs: Vec<JobResponse> = serde_json::from_str(&fixed_text)
+                        .map_err(|e| WhisperError::MalformedResponse(format!("Failed to parse as direct jobs array: {}", e)))?;
+                    Ok(self.owned_by(endpoint, jobs))
+                }
+            },
+            Err(_) => {
+                // Return empty list as fallback
+                Ok(Vec::new())
+            }
+        }
     }
 
-    let file_name = path
-        .file_name()
-        .context("Invalid file name")?
-        .to_str()
-        .context("Invalid file name encoding")?;
+    /// Remember that `jobs` live on `endpoint`.
+    fn owned_by(&self, endpoint: usize, jobs: Vec<JobResponse>) -> Vec<JobResponse> {
+        for job in &jobs {
+            self.endpoints.set_owner(&job.job_id, endpoint);
+        }
+        jobs
+    }
 
-    let file_content = tokio::fs::read(path)
-        .await
-        .context("Failed to read audio file")?;
-
-    let mime_type = mime_guess::from_path(path)
-        .first()
-        .context("Could not determine MIME type")?;
-
-    let form = multipart::Form::new().part(
-        "file",
-        multipart::Part::bytes(file_content)
-            .file_name(file_name.to_string())
-            .mime_str(mime_type.as_ref())
-            .context("Invalid MIME type")?,
-    );
-
-    let client = reqwest::Client::new();
-    let response = client
-        .post(&format!("{}/transcribe/", CONFIG.service_url))
-        .multipart(form)
-        .timeout(Duration::from_secs(3600))
-        .send()
-        .await
-        .context("Failed to send file to service")?;
+    pub async fn terminate_job(&self, job_id: &str) -> Result<JobResponse> {
+        let response = self
+            .execute_for_job(job_id, |base| self.http.delete(format!("{}/jobs/{}", base, job_id)))
+            .await?;
 
-    if !response.status().is_success() {
-        anyhow::bail!(
-            "Service error: {}",
-            response.text().await.unwrap_or_default()
-        );
+        parse_json(response).await
     }
 
-    let job_response: JobResponse = response
-        .json()
-        .await
-        .context("Failed to parse service response")?;
-
-    // Set up polling
-    let job_id = job_response.job_id.clone();
-    let mut status_interval = tokio::time::interval(Duration::from_secs(5));
-    let mut existence_check_interval = tokio::time::interval(Duration::from_secs(15));
-    
-    // Create a progress bar
-    let progress_bar = ProgressBar::new(100);
-    progress_bar.set_style(
-        ProgressStyle::default_bar()
-            .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {percent}% ({eta})")
-            .unwrap()
-            .progress_chars("#>-")
-    );
-    progress_bar.set_position(0);
-    
-    // Track the last reported progress to avoid duplicate updates
-    let mut last_progress_percent = 0.0;
-
-    loop {
-        tokio::select! {
-            _ = tokio::signal::ctrl_c() => {
-                progress_bar.abandon_with_message("Job terminated by user".red().to_string());
-                if let Ok(terminated) = terminate_job(&job_id).await {
-                    anyhow::bail!("Job terminated: {}", terminated.message);
+    /// Transcribe `path` with the transcription defaults from the config.
+    pub async fn transcribe_file(&self, path: &Path) -> Result<(TranscriptionResponse, JobResponse)> {
+        self.transcribe_file_with_progress(path, &self.config.transcription, &MultiProgress::new()).await
+    }
+
+    /// Like [`transcribe_file`](Self::transcribe_file), but with explicit `options` and
+    /// drawing the upload and transcription bars in `progress` so several jobs can share
+    /// one display.
+    pub async fn transcribe_file_with_progress(
+        &self,
+        path: &Path,
+        options: &TranscriptionOptions,
+        progress: &MultiProgress,
+    ) -> Result<(TranscriptionResponse, JobResponse)> {
+        self.transcribe_file_with(path, options, progress, None, &()).await
+    }
+
+    /// Like [`transcribe_file_with_progress`](Self::transcribe_file_with_progress), reporting
+    /// to `hooks`. With `reattach`, waits for that existing job of the file first, and only
+    /// submits the file again if the job is gone or its instance went down.
+    pub async fn transcribe_file_with(
+        &self,
+        path: &Path,
+        options: &TranscriptionOptions,
+        progress: &MultiProgress,
+        reattach: Option<&str>,
+        hooks: &dyn TranscribeHooks,
+    ) -> Result<(TranscriptionResponse, JobResponse)> {
+        let cache_key = self.cache_key(path, options).await?;
+        let cache = self.cache.as_ref().zip(cache_key.as_deref());
+        if let Some(record) = cache.and_then(|(cache, key)| cache.get(key)) {
+            hooks.cached();
+            return Ok((record.transcription, record.job));
+        }
+
+        let label = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.display().to_string());
+        let reattached = match reattach {
+            Some(job_id) => match self.attach_to_job_with_progress(job_id, &label, progress).await {
+                Err(e @ (WhisperError::JobNotFound(_) | WhisperError::JobTerminated { .. })) => {
+                    progress.suspend(|| eprintln!("Warning: {}; submitting {} again", e, label));
+                    None
+                }
+                Err(e) if self.can_fail_over(&e) => {
+                    progress.suspend(|| eprintln!("Warning: {}; submitting {} again", e, label));
+                    None
                 }
-                anyhow::bail!("Job terminated by user");
+                result => Some(result?),
+            },
+            None => None,
+        };
+        let (transcription, job) = match reattached {
+            Some(result) => result,
+            None => self.submit_and_wait(path, options, &label, progress, hooks).await?,
+        };
+
+        if let Some((cache, key)) = cache {
+            if let Err(e) = cache.put(key, &TranscriptRecord::new(transcription.clone(), &job)) {
+                progress.suspend(|| eprintln!("Warning: Failed to cache result: {:#}", e));
             }
-            _ = existence_check_interval.tick() => {
-                // Periodically check if the job still exists on the server
-                match check_job_exists(&job_id).await {
-                    Ok(exists) => {
-                        if !exists {
-                            progress_bar.abandon_with_message("Job no longer exists on server".red().to_string());
-                            anyhow::bail!("Job no longer exists on server. It may have been terminated externally.");
+        }
+        Ok((transcription, job))
+    }
+
+    /// Submit `path` and wait for its job, submitting it again while it fails for want of
+    /// an instance and there are others left to try.
+    async fn submit_and_wait(
+        &self,
+        path: &Path,
+        options: &TranscriptionOptions,
+        label: &str,
+        progress: &MultiProgress,
+        hooks: &dyn TranscribeHooks,
+    ) -> Result<(TranscriptionResponse, JobResponse)> {
+        let mut attempts = 1;
+        loop {
+            let job = self.submit_file(path, options, progress).await?;
+            hooks.submitted(&job);
+            match self.wait_for_job_with_progress(&job.job_id, label, progress).await {
+                Err(e) if attempts < self.endpoints.len() && self.can_fail_over(&e) => {
+                    progress.suspend(|| eprintln!("Warning: {}; submitting {} again", e, label));
+                    attempts += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Subscribe to the job's progress events, if the service offers them.
+    async fn open_events(&self, job_id: &str) -> Option<EventStream> {
+        let endpoint = match self.endpoints.owner(job_id) {
+            Some(endpoint) => endpoint,
+            None if self.endpoints.len() == 1 => 0,
+            // Asking an instance that doesn't have the job would look like it has no events
+            None => return None,
+        };
+        if self.endpoints.events_unavailable(endpoint) {
+            return None;
+        }
+        let request = self
+            .http
+            .get(self.url(endpoint, &format!("/events/{}", job_id)))
+            .headers(self.auth_headers.clone())
+            .header(reqwest::header::ACCEPT, "text/event-stream")
+            .send();
+        // Only until the headers arrive: the stream itself stays open for as long as the job runs
+        let response = match tokio::time::timeout(self.config.timeouts.request(), request).await {
+            Ok(response) => response.ok()?,
+            Err(_) => {
+                // Headers never came; polling won't hang like that, so stick to it
+                self.endpoints.set_events_unavailable(endpoint);
+                return None;
+            }
+        };
+
+        let is_event_stream = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with("text/event-stream"));
+        if response.status().is_success() && is_event_stream {
+            return Some(EventStream::new(response, self.config.timeouts.request()));
+        }
+        if response.status().is_success()
+            || matches!(
+                response.status(),
+                StatusCode::NOT_FOUND | StatusCode::METHOD_NOT_ALLOWED | StatusCode::NOT_IMPLEMENTED
+            )
+        {
+            // No events endpoint: don't ask again for later jobs
+            self.endpoints.set_events_unavailable(endpoint);
+        }
+        None
+    }
+
+    /// The job's next update, from its event stream or else by polling.
+    async fn next_status(&self, job_id: &str, source: &mut StatusSource) -> Result<JobResponse> {
+        if let StatusSource::Unopened = source {
+            if self.endpoints.owner(job_id).is_none() && self.endpoints.len() > 1 {
+                // Find the job's instance, so the events come from the right one
+                let _ = self.get_job_status(job_id, false).await;
+            }
+            // Prefer pushed updates; fall back to polling if the service can't stream them
+            *source = match self.open_events(job_id).await {
+                Some(events) => StatusSource::Events(events),
+                None => StatusSource::Polling { interval: MIN_POLL_INTERVAL, last: None },
+            };
+        }
+        if let StatusSource::Events(events) = source {
+            while let Some(data) = events.next_event().await {
+                // Skip events that aren't job updates
+                if let Some(event) = JobEvent::parse(&data) {
+                    return Ok(event.into_job(job_id));
+                }
+            }
+            // The stream ended before the job did
+            *source = StatusSource::Polling { interval: MIN_POLL_INTERVAL, last: None };
+        }
+
+        let StatusSource::Polling { interval, last } = source else {
+            unreachable!("sources are opened above, and event streams replaced by polling once they end");
+        };
+        tokio::time::sleep(*interval).await;
+        let status = self.get_job_status(job_id, false).await?;
+
+        let seen = (status.status.clone(), status.progress.as_ref().map(|p| p.percentage.to_bits()));
+        *interval = if last.as_ref() == Some(&seen) {
+            (*interval * 2).min(MAX_POLL_INTERVAL)
+        } else {
+            MIN_POLL_INTERVAL
+        };
+        *last = Some(seen);
+        Ok(status)
+    }
+
+    /// Upload a file and return the newly created job without waiting for it.
+    pub async fn submit_file(
+        &self,
+        path: &Path,
+        options: &TranscriptionOptions,
+        progress: &MultiProgress,
+    ) -> Result<JobResponse> {
+        let invalid_input = |message: &str| {
+            WhisperError::io(path, std::io::Error::new(std::io::ErrorKind::InvalidInput, message))
+        };
+
+        let file_name = path
+            .file_name()
+            .ok_or_else(|| invalid_input("Invalid file name"))?
+            .to_str()
+            .ok_or_else(|| invalid_input("Invalid file name encoding"))?;
+
+        let mime_type = mime_guess::from_path(path)
+            .first()
+            .ok_or_else(|| invalid_input("Could not determine MIME type"))?;
+
+        let file_size = tokio::fs::metadata(path)
+            .await
+            .map_err(|e| WhisperError::io(path, e))?
+            .len();
+
+        let upload_bar = progress.add(ProgressBar::new(file_size));
+        upload_bar.set_style(
+            ProgressStyle::default_bar()
+                .template("{spinner:.green} {prefix} Uploading [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta}) {msg}")
+                .unwrap()
+                .progress_chars("#>-")
+        );
+        upload_bar.set_prefix(file_name.to_string());
+
+        // The same key on every attempt lets a service that supports it drop duplicate uploads
+        let idempotency_key = format!("{:032x}", fastrand::u128(..));
+
+        let mut candidates = self.upload_candidates().await?.into_iter().peekable();
+        let response = loop {
+            let endpoint = candidates.next().expect("upload_candidates never returns an empty list");
+            let _uploading = self.endpoints.start_upload(endpoint);
+            // Uploads aren't idempotent, so the policy only repeats the ones the service can't have acted on
+            let response = self.config.retry.run(false, || async {
+                // Stream the file from disk rather than reading it into memory
+                let file = tokio::fs::File::open(path)
+                    .await
+                    .map_err(|e| WhisperError::io(path, e))?;
+                upload_bar.set_position(0);
+                let upload_progress = upload_bar.clone();
+                let stream = ReaderStream::new(file)
+                    .inspect_ok(move |chunk| upload_progress.inc(chunk.len() as u64));
+
+                let mut form = multipart::Form::new().part(
+                    "file",
+                    multipart::Part::stream_with_length(reqwest::Body::wrap_stream(stream), file_size)
+                        .file_name(file_name.to_string())
+                        .mime_str(mime_type.as_ref())
+                        .map_err(|_| invalid_input("Invalid MIME type"))?,
+                );
+                for (name, value) in options.form_fields() {
+                    form = form.text(name, value);
+                }
+
+                self.execute_once(
+                    self.http
+                        .post(self.url(endpoint, "/transcribe/"))
+                        .header("Idempotency-Key", &idempotency_key)
+                        .multipart(form)
+                        .timeout(self.config.timeouts.upload()),
+                )
+                .await
+            }).await;
+            match response {
+                Err(e) if e.is_unavailable() && candidates.peek().is_some() => {
+                    upload_bar.suspend(|| {
+                        eprintln!("Warning: Upload to {} failed: {}; trying the next endpoint", self.endpoints.url(endpoint), e)
+                    });
+                }
+                response => break response.map(|response| (endpoint, response)),
+            }
+        };
+
+        let (endpoint, response) = match response {
+            Ok(response) => {
+                upload_bar.finish_and_clear();
+                response
+            }
+            Err(e) => {
+                upload_bar.abandon_with_message("Upload failed".red().to_string());
+                return Err(e);
+            }
+        };
+
+        let job: JobResponse = parse_json(response).await?;
+        self.endpoints.set_owner(&job.job_id, endpoint);
+        Ok(job)
+    }
+
+    /// Instances to try an upload on, least busy first, leaving out any that are down.
+    async fn upload_candidates(&self) -> Result<Vec<usize>> {
+        if self.endpoints.len() == 1 {
+            return Ok(vec![0]);
+        }
+        let loads = self.endpoint_loads().await;
+        // Uploads still in flight don't show in the job lists yet
+        let active: Vec<Option<usize>> = loads
+            .iter()
+            .enumerate()
+            .map(|(endpoint, load)| load.active_jobs.as_ref().ok().map(|jobs| jobs + self.endpoints.uploading(endpoint)))
+            .collect();
+        let candidates = least_busy_first(&active);
+        if candidates.is_empty() {
+            let error = loads.into_iter().find_map(|load| load.active_jobs.err());
+            return Err(error.expect("every endpoint without a load has an error"));
+        }
+        Ok(candidates)
+    }
+
+    /// Wait for an existing job to finish and return its transcript.
+    pub async fn wait_for_job(&self, job_id: &str) -> Result<(TranscriptionResponse, JobResponse)> {
+        self.attach_to_job_with_progress(job_id, job_id, &MultiProgress::new()).await
+    }
+
+    /// Wait for a job this client submitted, drawing the progress bar in `progress`,
+    /// labelled with `label`. Ctrl-C terminates the job.
+    pub async fn wait_for_job_with_progress(
+        &self,
+        job_id: &str,
+        label: &str,
+        progress: &MultiProgress,
+    ) -> Result<(TranscriptionResponse, JobResponse)> {
+        self.wait_with_progress(job_id, label, progress, true).await
+    }
+
+    /// Like [`wait_for_job_with_progress`](Self::wait_for_job_with_progress), for a job
+    /// started elsewhere, e.g. with `--detach`. Ctrl-C only stops waiting and leaves the
+    /// job running, failing with [`WhisperError::Interrupted`].
+    pub async fn attach_to_job_with_progress(
+        &self,
+        job_id: &str,
+        label: &str,
+        progress: &MultiProgress,
+    ) -> Result<(TranscriptionResponse, JobResponse)> {
+        self.wait_with_progress(job_id, label, progress, false).await
+    }
+
+    async fn wait_with_progress(
+        &self,
+        job_id: &str,
+        label: &str,
+        progress: &MultiProgress,
+        terminate_on_interrupt: bool,
+    ) -> Result<(TranscriptionResponse, JobResponse)> {
+        let job_id = job_id.to_string();
+        // Opened by the first `next_status`, so Ctrl-C and the maximum wait apply meanwhile
+        let mut source = StatusSource::Unopened;
+        let max_wait = self.config.timeouts.max_wait();
+        let gave_up = async {
+            match max_wait {
+                Some(max_wait) => tokio::time::sleep(max_wait).await,
+                None => std::future::pending().await,
+            }
+        };
+        tokio::pin!(gave_up);
+
+        // Create a progress bar
+        let progress_bar = progress.add(ProgressBar::new(100));
+        progress_bar.set_style(
+            ProgressStyle::default_bar()
+                .template("{spinner:.green} {prefix} [{elapsed_precise}] [{bar:40.cyan/blue}] {percent}% ({eta}) {msg}")
+                .unwrap()
+                .progress_chars("#>-")
+        );
+        progress_bar.set_prefix(label.to_string());
+        progress_bar.set_position(0);
+
+        // Track the last reported progress to avoid duplicate updates
+        let mut last_progress_percent = 0.0;
+        let mut unavailable_polls = 0;
+
+        loop {
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => {
+                    if !terminate_on_interrupt {
+                        progress_bar.abandon_with_message("Stopped waiting, job left running".yellow().to_string());
+                        return Err(WhisperError::Interrupted { job_id });
+                    }
+                    progress_bar.abandon_with_message("Job terminated by user".red().to_string());
+                    let message = match self.terminate_job(&job_id).await {
+                        Ok(terminated) => terminated.message,
+                        Err(_) => "Job terminated by user".to_string(),
+                    };
+                    return Err(WhisperError::JobTerminated { job_id, message });
+                }
+                _ = &mut gave_up => {
+                    let waited = max_wait.unwrap_or_default();
+                    if self.config.timeouts.terminate_on_max_wait {
+                        progress_bar.abandon_with_message("Timed out, terminating job".red().to_string());
+                        if let Err(e) = self.terminate_job(&job_id).await {
+                            progress_bar.suspend(|| eprintln!("Warning: Failed to terminate job {}: {}", job_id, e));
                         }
-                    },
-                    Err(e) => {
-                        eprintln!("Warning: Failed to check if job exists: {}", e);
-                        // Continue processing even if check fails
+                    } else {
+                        progress_bar.abandon_with_message("Timed out, job left running".red().to_string());
                     }
+                    return Err(WhisperError::WaitTimeout { job_id, waited });
                 }
-            }
-            _ = status_interval.tick() => {
-                match get_job_status(&job_id, false).await {
-                    Ok(status) => {
-                        match status.status.as_str() {
-                            "completed" => {
-                                // Complete the progress bar
-                                progress_bar.set_position(100);
-                                progress_bar.finish_with_message("Transcription completed!".green().to_string());
-                                
-                                if let Some(ref result) = status.result {
-                                    return Ok((result.clone(), status));
+                status = self.next_status(&job_id, &mut source) => {
+                    match status {
+                        Ok(status) => {
+                            unavailable_polls = 0;
+                            match status.status {
+                                JobStatus::Completed => {
+                                    // Complete the progress bar
+                                    progress_bar.set_position(100);
+                                    progress_bar.finish_with_message("Transcription completed!".green().to_string());
+
+                                    if let Some(ref result) = status.result {
+                                        return Ok((result.clone(), status));
+                                    }
+
+                                    // If result is missing, try to fetch it with include_transcript=true
+                                    let full_status = self.get_job_status(&job_id, true).await?;
+                                    if let Some(ref result) = full_status.result {
+                                        return Ok((result.clone(), full_status));
+                                    }
+                                    return Err(WhisperError::MalformedResponse(
+                                        "No result in completed job, even after explicit fetch".to_string(),
+                                    ));
+                                }
+                                JobStatus::Failed => {
+                                    progress_bar.abandon_with_message(format!("Failed: {}", status.message).red().to_string());
+                                    return Err(WhisperError::JobFailed { job_id, message: status.message });
+                                }
+                                JobStatus::Terminated => {
+                                    progress_bar.abandon_with_message(format!("Terminated: {}", status.message).red().to_string());
+                                    return Err(WhisperError::JobTerminated { job_id, message: status.message });
+                                }
+                                JobStatus::Cancelled => {
+                                    progress_bar.abandon_with_message(format!("Cancelled: {}", status.message).red().to_string());
+                                    return Err(WhisperError::JobTerminated { job_id, message: status.message });
                                 }
-                                
-                                // If result is missing, try to fetch it with include_transcript=true
-                                match get_job_status(&job_id, true).await {
-                                    Ok(full_status) => {
-                                        if let Some(ref result) = full_status.result {
-                                            return Ok((result.clone(), full_status));
+                                JobStatus::Processing => {
+                                    // Ensure we're using the progress bar style (in case we were in queued state before)
+                                    progress_bar.set_style(
+                                        ProgressStyle::default_bar()
+                                            .template("{spinner:.green} {prefix} [{elapsed_precise}] [{bar:40.cyan/blue}] {percent}% ({eta}) {msg}")
+                                            .unwrap()
+                                            .progress_chars("#>-")
+                                    );
+
+                                    // Display progress information if available
+                                    if let Some(ref progress) = status.progress {
+                                        // Only update if progress has changed
+                                        if (progress.percentage - last_progress_percent).abs() > 0.1 {
+                                            last_progress_percent = progress.percentage;
+
+                                            // Update progress bar
+                                            progress_bar.set_position(progress.percentage as u64);
+
+                                            // Set message with detailed info
+                                            progress_bar.set_message(format!(
+                                                "Chunks: {}/{} | Duration: {:.1}/{:.1}s",
+                                                progress.processed_chunks,
+                                                progress.total_chunks,
+                                                progress.processed_duration,
+                                                progress.total_duration
+                                            ));
                                         }
-                                        anyhow::bail!("No result in completed job, even after explicit fetch");
-                                    },
-                                    Err(e) => {
-                                        anyhow::bail!("Job completed but failed to fetch result: {}", e);
+                                    } else {
+                                        // If no progress info, just pulse the bar
+                                        progress_bar.set_message("Processing...".to_string());
+                                        progress_bar.inc(0);
                                     }
                                 }
-                            }
-                            "failed" => {
-                                progress_bar.abandon_with_message(format!("Failed: {}", status.message).red().to_string());
-                                anyhow::bail!("Transcription failed: {}", status.message);
-                            }
-                            "terminated" => {
-                                progress_bar.abandon_with_message(format!("Terminated: {}", status.message).red().to_string());
-                                anyhow::bail!("Job was terminated: {}", status.message);
-                            }
-                            "cancelled" => {
-                                progress_bar.abandon_with_message(format!("Cancelled: {}", status.message).red().to_string());
-                                anyhow::bail!("Job was cancelled: {}", status.message);
-                            }
-                            "processing" => {
-                                // Ensure we're using the progress bar style (in case we were in queued state before)
-                                progress_bar.set_style(
-                                    ProgressStyle::default_bar()
-                                        .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {percent}% ({eta})")
-                                        .unwrap()
-                                        .progress_chars("#>-")
-                                );
-                                
-                                // Display progress information if available
-                                if let Some(ref progress) = status.progress {
-                                    // Only update if progress has changed
-                                    if (progress.percentage - last_progress_percent).abs() > 0.1 {
-                                        last_progress_percent = progress.percentage;
-                                        
-                                        // Update progress bar
-                                        progress_bar.set_position(progress.percentage as u64);
-                                        
-                                        // Set message with detailed info
-                                        progress_bar.set_message(format!(
-                                            "Chunks: {}/{} | Duration: {:.1}/{:.1}s",
-                                            progress.processed_chunks,
-                                            progress.total_chunks,
-                                            progress.processed_duration,
-                                            progress.total_duration
-                                        ));
-                                    }
-                                } else {
-                                    // If no progress info, just pulse the bar
-                                    progress_bar.set_message("Processing...".to_string());
-                                    progress_bar.inc(0);
+                                JobStatus::Queued => {
+                                    // Show a pulsing progress bar for queued state
+                                    progress_bar.set_style(
+                                        ProgressStyle::default_spinner()
+                                            .template("{spinner:.yellow} {prefix} {msg}")
+                                            .unwrap()
+                                    );
+                                    progress_bar.set_message("Queued: waiting to be processed".yellow().to_string());
+                                    progress_bar.tick();
+                                }
+                                JobStatus::Unknown(_) => {
+                                    // Unknown state, show warning in progress bar
+                                    progress_bar.set_message(format!("Unknown state: {}", status.status).yellow().to_string());
+                                    progress_bar.suspend(|| eprintln!("Job {} in unknown state: {}", job_id, status.status));
                                 }
                             }
-                            "queued" => {
-                                // Show a pulsing progress bar for queued state
-                                progress_bar.set_style(
-                                    ProgressStyle::default_spinner()
-                                        .template("{spinner:.yellow} {msg}")
-                                        .unwrap()
-                                );
-                                progress_bar.set_message("Queued: waiting to be processed".yellow().to_string());
-                                progress_bar.tick();
-                            }
-                            _ => {
-                                // Unknown state, show warning in progress bar
-                                progress_bar.set_message(format!("Unknown state: {}", status.status).yellow().to_string());
-                                eprintln!("Job {} in unknown state: {}", job_id, status.status);
-                            }
-                        }
-                    },
-                    Err(e) => {
-                        // If we can't get the status, the job might have been deleted
-                        if e.to_string().contains("404") || e.to_string().contains("not found") {
+                        },
+                        Err(WhisperError::JobNotFound(_)) => {
+                            // The job was deleted on the server
                             progress_bar.abandon_with_message("Job no longer exists on server".red().to_string());
-                            anyhow::bail!("Job no longer exists on server. It may have been terminated externally.");
-                        } else {
+                            return Err(WhisperError::JobNotFound(job_id));
+                        }
+                        Err(e) if self.can_fail_over(&e) && unavailable_polls + 1 >= MAX_UNAVAILABLE_POLLS => {
+                            // Its instance looks down; let the caller submit the file elsewhere
+                            progress_bar.abandon_with_message("Service instance unavailable".red().to_string());
+                            return Err(e);
+                        }
+                        Err(e) => {
+                            if e.is_unavailable() {
+                                unavailable_polls += 1;
+                            }
                             // For other errors, log and continue
                             progress_bar.set_message(format!("Warning: {}", e).yellow().to_string());
-                            eprintln!("Warning: Failed to get job status: {}", e);
+                            progress_bar.suspend(|| eprintln!("Warning: Failed to get job status: {}", e));
                             // Continue polling, but don't fail immediately on temporary errors
                         }
                     }
@@ -335,3 +896,84 @@ pub async fn transcribe_file(path: &PathBuf) -> Result<(TranscriptionResponse, J
         }
     }
 }
+
+async fn parse_json<T: DeserializeOwned>(response: reqwest::Response) -> Result<T> {
+    let text = response
+        .text()
+        .await
+        .map_err(|e| WhisperError::MalformedResponse(e.to_string()))?;
+    serde_json::from_str(&text).map_err(|e| WhisperError::MalformedResponse(e.to_string()))
+}
+
+/// `error` and its causes, e.g. "error sending request: tcp connect error: Connection refused".
+fn error_chain(error: &dyn std::error::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        // Some errors already include their cause in their own message
+        let cause_message = cause.to_string();
+        if !message.contains(&cause_message) {
+            message.push_str(&format!(": {}", cause_message));
+        }
+        source = cause.source();
+    }
+    message
+}
+
+// Free-function wrappers around a client built from the user's config file.
+
+pub async fn check_service() -> Result<()> {
+    WhisperClient::from_config_file()?.check_service().await
+}
+
+pub async fn get_job_status(job_id: &str, include_transcript: bool) -> Result<JobResponse> {
+    WhisperClient::from_config_file()?.get_job_status(job_id, include_transcript).await
+}
+
+pub async fn list_jobs() -> Result<Vec<JobResponse>> {
+    WhisperClient::from_config_file()?.list_jobs().await
+}
+
+pub async fn terminate_job(job_id: &str) -> Result<JobResponse> {
+    WhisperClient::from_config_file()?.terminate_job(job_id).await
+}
+
+pub async fn transcribe_file(path: &Path) -> Result<(TranscriptionResponse, JobResponse)> {
+    WhisperClient::from_config_file()?.transcribe_file(path).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder_overrides_service_url() {
+        let client = WhisperClient::builder()
+            .config(Config::with_url("http://a:1".to_string()))
+            .service_url("http://b:2/")
+            .build()
+            .unwrap();
+        assert_eq!(client.service_url(), "http://b:2");
+        assert_eq!(client.url(0, "/jobs"), "http://b:2/jobs");
+    }
+
+    #[test]
+    fn test_endpoints_start_with_service_url() {
+        let config = Config {
+            endpoints: vec!["http://b:2/".to_string(), "http://a:1".to_string()],
+            ..Config::with_url("http://a:1".to_string())
+        };
+        let client = WhisperClient::new(config).unwrap();
+        assert_eq!(client.endpoints(), ["http://a:1", "http://b:2"]);
+        assert_eq!(client.url(1, "/jobs"), "http://b:2/jobs");
+        assert_eq!(client.job_endpoint("job-1"), None);
+        assert!(client.can_fail_over(&WhisperError::Timeout { url: "http://a:1".to_string() }));
+    }
+
+    #[test]
+    fn test_independent_clients() {
+        let a = WhisperClient::new(Config::with_url("http://a:1".to_string())).unwrap();
+        let b = WhisperClient::new(Config::with_url("http://b:2".to_string())).unwrap();
+        assert_ne!(a.service_url(), b.service_url());
+    }
+}
@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use crate::error::Result;
+
+/// The whisper-service instances a client spreads jobs across, and which job lives where.
+#[derive(Debug)]
+pub(crate) struct Endpoints {
+    /// Base URLs without a trailing slash, the configured `service_url` first.
+    urls: Vec<String>,
+    /// Index into `urls` of the instance that owns each job seen so far.
+    owners: Mutex<HashMap<String, usize>>,
+    /// Uploads in flight to each instance, which its job list doesn't show yet.
+    uploading: Vec<AtomicUsize>,
+    /// Set for instances that turn out not to have an events endpoint.
+    events_unavailable: Vec<AtomicBool>,
+}
+
+impl Endpoints {
+    pub(crate) fn new(service_url: &str, others: &[String]) -> Self {
+        let mut urls: Vec<String> = Vec::new();
+        for url in std::iter::once(service_url).chain(others.iter().map(String::as_str)) {
+            let url = url.trim_end_matches('/').to_string();
+            if !urls.contains(&url) {
+                urls.push(url);
+            }
+        }
+        Self {
+            owners: Mutex::new(HashMap::new()),
+            uploading: urls.iter().map(|_| AtomicUsize::new(0)).collect(),
+            events_unavailable: urls.iter().map(|_| AtomicBool::new(false)).collect(),
+            urls,
+        }
+    }
+
+    pub(crate) fn urls(&self) -> &[String] {
+        &self.urls
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.urls.len()
+    }
+
+    pub(crate) fn url(&self, index: usize) -> &str {
+        &self.urls[index]
+    }
+
+    pub(crate) fn owner(&self, job_id: &str) -> Option<usize> {
+        self.owners.lock().unwrap().get(job_id).copied()
+    }
+
+    pub(crate) fn set_owner(&self, job_id: &str, index: usize) {
+        self.owners.lock().unwrap().insert(job_id.to_string(), index);
+    }
+
+    pub(crate) fn uploading(&self, index: usize) -> usize {
+        self.uploading[index].load(Ordering::SeqCst)
+    }
+
+    /// Count an upload to `index` until the returned guard is dropped.
+    pub(crate) fn start_upload(&self, index: usize) -> Uploading<'_> {
+        self.uploading[index].fetch_add(1, Ordering::SeqCst);
+        Uploading(&self.uploading[index])
+    }
+
+    pub(crate) fn events_unavailable(&self, index: usize) -> bool {
+        self.events_unavailable[index].load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn set_events_unavailable(&self, index: usize) {
+        self.events_unavailable[index].store(true, Ordering::Relaxed);
+    }
+}
+
+/// An upload in flight, counted against its endpoint's load.
+pub(crate) struct Uploading<'a>(&'a AtomicUsize);
+
+impl Drop for Uploading<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// How busy a service instance is.
+#[derive(Debug)]
+pub struct EndpointLoad {
+    pub url: String,
+    /// Its queued and processing jobs, or why it couldn't be checked.
+    pub active_jobs: Result<usize>,
+}
+
+/// Indices of the reachable endpoints (those with a load), least busy first.
+/// Ties go to the endpoint listed first.
+pub(crate) fn least_busy_first(loads: &[Option<usize>]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..loads.len()).filter(|&index| loads[index].is_some()).collect();
+    order.sort_by_key(|&index| loads[index]);
+    order
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_dedupes_urls() {
+        let endpoints = Endpoints::new(
+            "http://a:9673/",
+            &["http://b:9673".to_string(), "http://a:9673".to_string()],
+        );
+        assert_eq!(endpoints.urls(), ["http://a:9673", "http://b:9673"]);
+    }
+
+    #[test]
+    fn test_owners_and_uploads() {
+        let endpoints = Endpoints::new("http://a:9673", &["http://b:9673".to_string()]);
+        assert_eq!(endpoints.owner("job-1"), None);
+        endpoints.set_owner("job-1", 1);
+        assert_eq!(endpoints.owner("job-1"), Some(1));
+
+        let upload = endpoints.start_upload(1);
+        assert_eq!(endpoints.uploading(1), 1);
+        drop(upload);
+        assert_eq!(endpoints.uploading(1), 0);
+    }
+
+    #[test]
+    fn test_least_busy_first() {
+        assert_eq!(least_busy_first(&[Some(3), None, Some(1), Some(3)]), vec![2, 0, 3]);
+        assert_eq!(least_busy_first(&[Some(0)]), vec![0]);
+        assert!(least_busy_first(&[None, None]).is_empty());
+    }
+}
@@ -0,0 +1,130 @@
+use reqwest::StatusCode;
+use std::path::PathBuf;
+
+pub type Result<T> = std::result::Result<T, WhisperError>;
+
+/// Errors returned by [`crate::WhisperClient`].
+#[derive(Debug, thiserror::Error)]
+pub enum WhisperError {
+    /// The service could not be reached.
+    #[error("Failed to connect to Whisper service at {url}: {source}")]
+    Connection {
+        url: String,
+        #[source]
+        source: reqwest::Error,
+    },
+
+    /// The proxy could not be reached, or refused to forward the request.
+    #[error("Proxy {proxy} failed: {message}")]
+    Proxy { proxy: String, message: String },
+
+    /// A request did not complete in time.
+    #[error("Request to {url} timed out")]
+    Timeout { url: String },
+
+    /// The job did not finish within the configured maximum wait.
+    #[error("Gave up waiting for job {job_id} after {}s", waited.as_secs())]
+    WaitTimeout { job_id: String, waited: std::time::Duration },
+
+    /// The service answered with a non-success status.
+    #[error("Service returned {status}: {body}")]
+    Http { status: StatusCode, body: String },
+
+    /// The job does not exist on the server (any more).
+    #[error("Job {0} not found on server")]
+    JobNotFound(String),
+
+    /// The service reported that the job failed.
+    #[error("Transcription failed: {message}")]
+    JobFailed { job_id: String, message: String },
+
+    /// The job was terminated or cancelled, either by us or externally.
+    #[error("Job was terminated: {message}")]
+    JobTerminated { job_id: String, message: String },
+
+    /// Waiting was interrupted with Ctrl-C, leaving the job running on the server.
+    #[error("Stopped waiting for job {job_id}; it is still running on the server")]
+    Interrupted { job_id: String },
+
+    /// The service answered with a body we could not understand.
+    #[error("Malformed response from service: {0}")]
+    MalformedResponse(String),
+
+    /// A local file could not be read or written.
+    #[error("{}: {source}", path.display())]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    /// The client configuration is invalid.
+    #[error("Invalid configuration: {0}")]
+    Config(String),
+}
+
+impl WhisperError {
+    pub(crate) fn from_transport(url: &str, source: reqwest::Error) -> Self {
+        if source.is_timeout() {
+            WhisperError::Timeout { url: url.to_string() }
+        } else {
+            WhisperError::Connection { url: url.to_string(), source }
+        }
+    }
+
+    pub(crate) fn io(path: impl Into<PathBuf>, source: std::io::Error) -> Self {
+        WhisperError::Io { path: path.into(), source }
+    }
+
+    /// Whether the service instance looks down or overloaded, rather than the request
+    /// being at fault, so another instance may do better.
+    pub fn is_unavailable(&self) -> bool {
+        match self {
+            WhisperError::Connection { .. } | WhisperError::Timeout { .. } => true,
+            WhisperError::Http { status, .. } => matches!(
+                *status,
+                StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE | StatusCode::GATEWAY_TIMEOUT
+            ),
+            _ => false,
+        }
+    }
+
+    /// Turn a 404 from a job endpoint into [`WhisperError::JobNotFound`].
+    pub(crate) fn for_job(self, job_id: &str) -> Self {
+        match self {
+            WhisperError::Http { status, .. } if status == StatusCode::NOT_FOUND => {
+                WhisperError::JobNotFound(job_id.to_string())
+            }
+            other => other,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_for_job_maps_404() {
+        let err = WhisperError::Http {
+            status: StatusCode::NOT_FOUND,
+            body: "whatever the server says".to_string(),
+        };
+        assert!(matches!(err.for_job("abc"), WhisperError::JobNotFound(id) if id == "abc"));
+
+        let err = WhisperError::Http {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            body: String::new(),
+        };
+        assert!(matches!(err.for_job("abc"), WhisperError::Http { .. }));
+    }
+
+    #[test]
+    fn test_is_unavailable() {
+        let http = |status: StatusCode| WhisperError::Http { status, body: String::new() };
+        assert!(WhisperError::Timeout { url: "http://a".to_string() }.is_unavailable());
+        assert!(http(StatusCode::SERVICE_UNAVAILABLE).is_unavailable());
+        assert!(!http(StatusCode::BAD_REQUEST).is_unavailable());
+        assert!(!WhisperError::JobNotFound("abc".to_string()).is_unavailable());
+    }
+}
@@ -0,0 +1,126 @@
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::time::Duration;
+use crate::models::{JobResponse, JobStatus, Progress};
+
+/// Splits a `text/event-stream` body into the data of its events.
+#[derive(Debug, Default)]
+pub(crate) struct EventParser {
+    buffer: Vec<u8>,
+    data: Vec<String>,
+}
+
+impl EventParser {
+    /// Feed the next chunk of the body, returning the data of every event it completes.
+    pub(crate) fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.buffer.extend_from_slice(chunk);
+        let mut events = Vec::new();
+        while let Some(end) = self.buffer.iter().position(|&byte| byte == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\r', '\n']);
+            if line.is_empty() {
+                if !self.data.is_empty() {
+                    events.push(self.data.join("\n"));
+                    self.data.clear();
+                }
+            } else if let Some(value) = line.strip_prefix("data:") {
+                self.data.push(value.strip_prefix(' ').unwrap_or(value).to_string());
+            }
+            // Comments and the event, id and retry fields aren't needed
+        }
+        events
+    }
+}
+
+/// Progress events for one job, read from a streaming response.
+#[derive(Debug)]
+pub(crate) struct EventStream {
+    response: reqwest::Response,
+    /// How long the stream may stay silent (not even a keep-alive) before it counts as broken.
+    idle_timeout: Duration,
+    parser: EventParser,
+    pending: VecDeque<String>,
+}
+
+impl EventStream {
+    pub(crate) fn new(response: reqwest::Response, idle_timeout: Duration) -> Self {
+        Self { response, idle_timeout, parser: EventParser::default(), pending: VecDeque::new() }
+    }
+
+    /// The data of the next event, or `None` once the stream has ended, broken or gone idle.
+    pub(crate) async fn next_event(&mut self) -> Option<String> {
+        loop {
+            if let Some(data) = self.pending.pop_front() {
+                return Some(data);
+            }
+            let chunk = tokio::time::timeout(self.idle_timeout, self.response.chunk()).await.ok()?.ok()??;
+            self.pending.extend(self.parser.push(&chunk));
+        }
+    }
+}
+
+/// A job update as sent on the event stream. Events with only `progress` mean processing.
+#[derive(Debug, Deserialize)]
+pub(crate) struct JobEvent {
+    status: Option<JobStatus>,
+    #[serde(default)]
+    message: String,
+    progress: Option<Progress>,
+}
+
+impl JobEvent {
+    /// The job update in an event's data, or `None` for anything else, such as a heartbeat.
+    pub(crate) fn parse(data: &str) -> Option<Self> {
+        let event: JobEvent = serde_json::from_str(data).ok()?;
+        (event.status.is_some() || event.progress.is_some()).then_some(event)
+    }
+
+    pub(crate) fn into_job(self, job_id: &str) -> JobResponse {
+        JobResponse {
+            job_id: job_id.to_string(),
+            status: self.status.unwrap_or(JobStatus::Processing),
+            message: self.message,
+            result: None,
+            file_info: None,
+            created_at: None,
+            filename: None,
+            progress: self.progress,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_parser_handles_split_chunks() {
+        let mut parser = EventParser::default();
+        assert!(parser.push(b": keep-alive\n\nevent: progress\r\ndata: {\"status\":").is_empty());
+        assert_eq!(parser.push(b" \"queued\"}\r\n\r\ndata: a\ndata: b\n"), vec!["{\"status\": \"queued\"}"]);
+        assert_eq!(parser.push(b"\n"), vec!["a\nb"]);
+    }
+
+    #[test]
+    fn test_job_event_into_job() {
+        let event = JobEvent::parse(
+            r#"{"progress": {"total_duration": 10.0, "processed_duration": 5.0, "total_chunks": 2,
+                "processed_chunks": 1, "percentage": 50.0}}"#,
+        ).unwrap();
+        let job = event.into_job("job-1");
+        assert_eq!(job.job_id, "job-1");
+        assert_eq!(job.status, JobStatus::Processing);
+        assert_eq!(job.progress.unwrap().percentage, 50.0);
+
+        let event = JobEvent::parse(r#"{"status": "failed", "message": "boom"}"#).unwrap();
+        let job = event.into_job("job-1");
+        assert_eq!(job.status, JobStatus::Failed);
+        assert_eq!(job.message, "boom");
+
+        // Neither a status nor progress: not a job update
+        assert!(JobEvent::parse(r#"{"type": "heartbeat"}"#).is_none());
+        assert!(JobEvent::parse("{}").is_none());
+        assert!(JobEvent::parse("not json").is_none());
+    }
+}
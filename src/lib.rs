@@ -1,13 +1,49 @@
+mod auth;
+mod cache;
 mod client;
+mod endpoints;
+mod error;
+mod events;
+mod manifest;
 mod models;
+mod output;
+mod paths;
+mod proxy;
+mod retry;
+mod tls;
 mod utils;
 mod config;
 
 // Re-export types needed for the public API
-pub use client::{check_service, get_job_status, list_jobs, transcribe_file, terminate_job};
-pub use models::{FileInfo, JobResponse, Segment, TranscriptionResponse};
-pub use utils::{collect_audio_files, is_supported_audio_format, save_markdown_response, get_supported_formats};
-pub use config::Config;
+pub use client::{
+    check_service, get_job_status, list_jobs, transcribe_file, terminate_job,
+    TranscribeHooks, WhisperClient, WhisperClientBuilder,
+};
+pub use models::{
+    FileInfo, JobResponse, JobStatus, Progress, Segment, Task, TranscriptRecord, TranscriptionOptions,
+    TranscriptionResponse,
+};
+pub use utils::{
+    collect_audio_files, is_supported_audio_format, save_markdown_response, get_supported_formats,
+    should_skip, SkipPolicy,
+};
+pub use output::{
+    format_srt, format_srt_timestamp, format_vtt, format_vtt_timestamp,
+    FormatterRegistry, JsonFormatter, MarkdownFormatter, OutputFormatter, SrtFormatter, TextFormatter, VttFormatter,
+};
+pub use auth::{redact_url_password, AuthConfig, BasicAuth, Secret};
+pub use cache::{parse_age, CacheStats, ResultCache};
+pub use endpoints::EndpointLoad;
+pub use config::{
+    config_path, env_var_for, migrate_legacy_config, Config, ConfigFile, Profile, Timeouts, CONFIG_ENV, PROFILE_ENV,
+    READ_ONLY_ENV,
+};
+pub use paths::{cache_dir, config_dir, state_dir};
+pub use proxy::ProxyConfig;
+pub use retry::RetryPolicy;
+pub use tls::TlsConfig;
+pub use error::WhisperError;
+pub use manifest::{BatchManifest, FileOutcome, ManifestEntry, ResumeAction};
 
 // Re-export command line types
 use clap::Parser;
@@ -19,20 +55,136 @@ use clap::Parser;
   whisper-client transcribe ./recordings/ --recursive
   whisper-client list-jobs
   whisper-client status --job-id <ID>
-  whisper-client terminate --job-id <ID>")]
+  whisper-client transcribe ./recordings/ --detach
+  whisper-client wait --job-id <ID>
+  whisper-client fetch --job-id <ID> --out talk.srt
+  whisper-client terminate --job-id <ID>
+  whisper-client cache stats
+  whisper-client config set timeouts.request_secs 60")]
 pub struct Args {
-    /// Command to execute (transcribe, list-jobs, status, terminate, info)
+    /// Command to execute (transcribe, transcribe-youtube, list-jobs, status, wait, fetch, terminate,
+    /// cache, config, info)
     #[arg(value_enum)]
     pub command: Option<Command>,
 
-    /// Path to audio file or directory of audio files (required for transcribe command)
+    /// Path to audio file or directory of audio files (required for transcribe command).
+    /// For the cache command, the action: stats, clear or prune.
+    /// For the config command, the action: path, dirs, get, set, init or validate
     #[arg(name = "PATH")]
     pub path: Option<std::path::PathBuf>,
 
+    /// For config get and set: the key, followed by the value for set
+    #[arg(name = "ARGS")]
+    pub config_args: Vec<String>,
+
     /// Process directory recursively (only valid with directory input)
     #[arg(short, long)]
     pub recursive: bool,
 
+    /// Number of files to transcribe at once when processing a directory
+    #[arg(short = 'j', long, default_value_t = 1, value_parser = clap::value_parser!(u16).range(1..))]
+    pub concurrency: u16,
+
+    /// Resume an interrupted transcribe run: skip files already done and re-attach to in-flight jobs
+    #[arg(long)]
+    pub resume: bool,
+
+    /// Skip inputs whose output files (for every selected format) already exist
+    #[arg(long)]
+    pub skip_existing: bool,
+
+    /// Skip inputs whose output files all exist and are newer than the input
+    #[arg(long)]
+    pub skip_if_newer: bool,
+
+    /// Transcribe every input, overriding --skip-existing and --skip-if-newer
+    #[arg(long)]
+    pub force: bool,
+
+    /// Upload the files, print their job IDs and exit without waiting for the results
+    #[arg(long)]
+    pub detach: bool,
+
+    /// Always upload, ignoring (and not updating) the local result cache
+    #[arg(long)]
+    pub no_cache: bool,
+
+    /// With `cache prune`: remove entries older than this (e.g. 30d, 12h)
+    #[arg(long, value_name = "AGE")]
+    pub older_than: Option<String>,
+
+    /// Spoken language as an ISO 639-1 code (e.g. en, de); detected by the service when unset
+    #[arg(long)]
+    pub language: Option<String>,
+
+    /// Whisper model to use (e.g. base, small, large-v3)
+    #[arg(long)]
+    pub model: Option<String>,
+
+    /// Transcribe in the spoken language, or translate to English
+    #[arg(long, value_enum)]
+    pub task: Option<Task>,
+
+    /// Text to prime the model with, such as names and domain vocabulary
+    #[arg(long)]
+    pub initial_prompt: Option<String>,
+
+    /// Sampling temperature (0 for the most deterministic output)
+    #[arg(long)]
+    pub temperature: Option<f32>,
+
+    /// Ask the service for word-level timestamps
+    #[arg(long)]
+    pub word_timestamps: bool,
+
+    /// Seconds to wait for a connection to the service
+    #[arg(long, value_name = "SECS")]
+    pub connect_timeout: Option<u64>,
+
+    /// Seconds to wait for each status, list, terminate or health request
+    #[arg(long, value_name = "SECS")]
+    pub request_timeout: Option<u64>,
+
+    /// Seconds to wait for an upload to finish
+    #[arg(long, value_name = "SECS")]
+    pub upload_timeout: Option<u64>,
+
+    /// Give up waiting for a job after this many seconds
+    #[arg(long, value_name = "SECS")]
+    pub max_wait: Option<u64>,
+
+    /// Terminate the job on the server when --max-wait runs out
+    #[arg(long)]
+    pub terminate_on_timeout: bool,
+
+    /// Send every request through this proxy (http://, https://, socks5:// or socks5h:// URL)
+    #[arg(long, value_name = "URL")]
+    pub proxy: Option<String>,
+
+    /// Comma-separated hosts or domains to reach without the proxy
+    #[arg(long, value_name = "HOSTS", value_delimiter = ',')]
+    pub no_proxy: Vec<String>,
+
+    /// Use this profile from the config file instead of WHISPER_CLIENT_PROFILE or default_profile
+    #[arg(long, value_name = "NAME")]
+    pub profile: Option<String>,
+
+    /// Service URL to use, overriding the config file and WHISPER_CLIENT_URL
+    #[arg(long, value_name = "URL")]
+    pub service_url: Option<String>,
+
+    /// Override a config key for this run, e.g. --set timeouts.request_secs=5 (repeatable)
+    #[arg(long = "set", value_name = "KEY=VALUE", value_parser = parse_key_value)]
+    pub overrides: Vec<(String, String)>,
+
+    /// Config file to use instead of the default one (also WHISPER_CLIENT_CONFIG)
+    #[arg(long = "config", value_name = "PATH")]
+    pub config_file: Option<std::path::PathBuf>,
+
+    /// Never create the config file; use the defaults if there is none
+    #[arg(long)]
+    pub read_only: bool,
+
     /// YouTube URL (required for transcribe-youtube command)
     #[arg(long = "url")]
     pub url: Option<String>,
@@ -41,13 +193,100 @@ pub struct Args {
     #[arg(long)]
     pub output_dir: Option<std::path::PathBuf>,
 
-    /// Job ID (required for status and terminate commands)
-    #[arg(name = "JOB_ID", long)]
+    /// With `fetch`: write the transcript to this file (format from --format or its extension)
+    #[arg(long, value_name = "FILE")]
+    pub out: Option<std::path::PathBuf>,
+
+    /// Job ID (required for status, wait, fetch and terminate commands)
+    #[arg(long = "job-id", value_name = "JOB_ID")]
     pub job_id: Option<String>,
 
     /// Show detailed output including segments
     #[arg(short, long)]
     pub verbose: bool,
+
+    /// Output format(s) for transcripts: md, txt, json, srt, vtt. Repeat or comma-separate
+    /// for several (transcribe defaults to md; status only saves when given)
+    #[arg(long = "format", value_name = "FORMAT", value_delimiter = ',')]
+    pub formats: Vec<String>,
+
+    /// WebVTT cue settings added to every cue (e.g. "line:90% align:center")
+    #[arg(long)]
+    pub vtt_cue_settings: Option<String>,
+}
+
+fn parse_key_value(text: &str) -> std::result::Result<(String, String), String> {
+    let (key, value) = text.split_once('=').ok_or_else(|| format!("expected KEY=VALUE, got {:?}", text))?;
+    Ok((key.trim().to_string(), value.to_string()))
+}
+
+impl Args {
+    /// The skip policy selected by --skip-existing, --skip-if-newer and --force.
+    pub fn skip_policy(&self) -> SkipPolicy {
+        if self.force {
+            SkipPolicy::Never
+        } else if self.skip_if_newer {
+            SkipPolicy::IfNewer
+        } else if self.skip_existing {
+            SkipPolicy::Existing
+        } else {
+            SkipPolicy::Never
+        }
+    }
+
+    /// The config file in use: --config, else the default from [`config_path`].
+    pub fn config_path(&self) -> anyhow::Result<std::path::PathBuf> {
+        match &self.config_file {
+            Some(path) => Ok(path.clone()),
+            None => config_path(),
+        }
+    }
+
+    /// Config overrides given on the command line: every --set, then --service-url.
+    pub fn config_overrides(&self) -> Vec<(String, String)> {
+        let mut overrides = self.overrides.clone();
+        if let Some(url) = &self.service_url {
+            overrides.push(("service_url".to_string(), url.clone()));
+        }
+        overrides
+    }
+
+    /// `config` with the timeouts given on the command line applied.
+    pub fn timeouts(&self, config: &Timeouts) -> Timeouts {
+        Timeouts {
+            connect_secs: self.connect_timeout.unwrap_or(config.connect_secs),
+            request_secs: self.request_timeout.unwrap_or(config.request_secs),
+            upload_secs: self.upload_timeout.unwrap_or(config.upload_secs),
+            max_wait_secs: self.max_wait.or(config.max_wait_secs),
+            terminate_on_max_wait: self.terminate_on_timeout || config.terminate_on_max_wait,
+        }
+    }
+
+    /// `config` with the proxy settings given on the command line applied.
+    pub fn proxy(&self, config: &ProxyConfig) -> ProxyConfig {
+        let mut proxy = config.clone();
+        if let Some(url) = &self.proxy {
+            // An explicit --proxy is for everything, whatever the config says per scheme
+            proxy = ProxyConfig { all: Some(url.clone()), ..ProxyConfig::default() };
+            proxy.no_proxy = config.no_proxy.clone();
+        }
+        if !self.no_proxy.is_empty() {
+            proxy.no_proxy = self.no_proxy.clone();
+        }
+        proxy
+    }
+
+    /// The transcription options given on the command line; unset ones fall back to the config.
+    pub fn transcription_options(&self) -> TranscriptionOptions {
+        TranscriptionOptions {
+            language: self.language.clone(),
+            model: self.model.clone(),
+            task: self.task,
+            initial_prompt: self.initial_prompt.clone(),
+            temperature: self.temperature,
+            word_timestamps: self.word_timestamps.then_some(true),
+        }
+    }
 }
 
 impl Default for Args {
@@ -55,11 +294,41 @@ impl Default for Args {
         Self {
             command: Some(Command::Info),
             path: None,
+            config_args: Vec::new(),
             recursive: false,
+            concurrency: 1,
+            resume: false,
+            skip_existing: false,
+            skip_if_newer: false,
+            force: false,
+            detach: false,
+            no_cache: false,
+            older_than: None,
+            language: None,
+            model: None,
+            task: None,
+            initial_prompt: None,
+            temperature: None,
+            word_timestamps: false,
+            connect_timeout: None,
+            request_timeout: None,
+            upload_timeout: None,
+            max_wait: None,
+            terminate_on_timeout: false,
+            proxy: None,
+            no_proxy: Vec::new(),
+            profile: None,
+            service_url: None,
+            overrides: Vec::new(),
+            config_file: None,
+            read_only: false,
             job_id: None,
             verbose: false,
             url: None,
             output_dir: None,
+            out: None,
+            formats: Vec::new(),
+            vtt_cue_settings: None,
         }
     }
 }
@@ -74,8 +343,16 @@ pub enum Command {
     ListJobs,
     /// Get status of a specific job
     Status,
+    /// Wait for a running job and save its transcript
+    Wait,
+    /// Save the transcript of a completed job
+    Fetch,
     /// Terminate a specific job
     Terminate,
     /// Show service information and available commands
     Info,
+    /// Manage the local result cache (stats, clear, prune)
+    Cache,
+    /// Inspect or change the configuration (path, dirs, get, set, init, validate)
+    Config,
 }
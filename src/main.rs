@@ -1,32 +1,77 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use colored::*;
 mod youtube;
 
 use whisper_client::{
-    Args, Command,
-    check_service, list_jobs, get_job_status, transcribe_file, terminate_job,
-    collect_audio_files, save_markdown_response, get_supported_formats, Config,
+    Args, BatchManifest, SkipPolicy, Command, Config, ConfigFile, FormatterRegistry, JobResponse, JobStatus, OutputFormatter,
+    ResultCache, ResumeAction, TranscriptionOptions, TranscriptionResponse, TranscribeHooks, VttFormatter, WhisperClient, WhisperError,
+    cache_dir, collect_audio_files, get_supported_formats, migrate_legacy_config, parse_age, redact_url_password,
+    should_skip, state_dir,
 };
 use clap::Parser;
-use std::collections::HashMap;
+use futures_util::{stream, StreamExt};
+use indicatif::MultiProgress;
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 
-async fn display_service_info() -> Result<()> {
+/// Map a client error to the process exit code documented in docs/usage.md.
+fn exit_code(err: &WhisperError) -> i32 {
+    match err {
+        WhisperError::Connection { .. } => 3,
+        WhisperError::Timeout { .. } | WhisperError::WaitTimeout { .. } => 4,
+        WhisperError::Http { .. } => 5,
+        WhisperError::JobNotFound(_) => 6,
+        WhisperError::JobFailed { .. } => 7,
+        WhisperError::JobTerminated { .. } => 8,
+        WhisperError::MalformedResponse(_) => 9,
+        WhisperError::Io { .. } => 10,
+        WhisperError::Config(_) => 11,
+        WhisperError::Proxy { .. } => 12,
+        // The usual code for a process stopped by SIGINT
+        WhisperError::Interrupted { .. } => 130,
+    }
+}
+
+/// Short status marker used by the list-jobs and status commands.
+fn status_marker(status: &JobStatus) -> ColoredString {
+    match status {
+        JobStatus::Completed => "✓".green(),
+        JobStatus::Failed => "✗".red(),
+        _ => "⋯".blue(),
+    }
+}
+
+async fn display_service_info(client: &WhisperClient) -> Result<()> {
     // Check service status
-    let service_status = match check_service().await {
+    let service_status = match client.check_service().await {
         Ok(_) => ("✓".green(), "Running"),
         Err(_) => ("✗".red(), "Not available"),
     };
     
-    let config = Config::load()?;
     println!("\n{} Service Status: {} {}", "🔍".blue(), service_status.0, service_status.1);
-    println!("   URL: {}", config.service_url);
+    if let Some(profile) = &client.config().active_profile {
+        println!("   Profile: {}", profile);
+    }
+    if client.endpoints().len() > 1 {
+        println!("   Endpoints:");
+        for load in client.endpoint_loads().await {
+            match load.active_jobs {
+                Ok(active) => println!("     {} {} ({} active jobs)", "✓".green(), redact_url_password(&load.url), active),
+                Err(e) => println!("     {} {} ({})", "✗".red(), redact_url_password(&load.url), e),
+            }
+        }
+    } else {
+        println!("   URL: {}", redact_url_password(client.service_url()));
+    }
     
     // Only try to get jobs if service is running
     if service_status.1 == "Running" {
-        match list_jobs().await {
+        match client.list_jobs().await {
             Ok(jobs) => {
                 // Count jobs by status
-                let mut status_counts: HashMap<String, usize> = HashMap::new();
+                let mut status_counts: HashMap<JobStatus, usize> = HashMap::new();
                 for job in &jobs {
                     *status_counts.entry(job.status.clone()).or_insert(0) += 1;
                     
@@ -36,11 +81,11 @@ async fn display_service_info() -> Result<()> {
                 if !jobs.is_empty() {
                     println!("\n{} Job Summary:", "📊".blue());
                     for (status, count) in status_counts {
-                        let status_icon = match status.as_str() {
-                            "completed" => "✓".green(),
-                            "failed" => "✗".red(),
-                            "processing" => "⚙️".blue(),
-                            "queued" => "⏳".yellow(),
+                        let status_icon = match status {
+                            JobStatus::Completed => "✓".green(),
+                            JobStatus::Failed => "✗".red(),
+                            JobStatus::Processing => "⚙️".blue(),
+                            JobStatus::Queued => "⏳".yellow(),
                             _ => "•".normal(),
                         };
                         println!("   {} {} jobs {}", status_icon, count, status);
@@ -48,14 +93,14 @@ async fn display_service_info() -> Result<()> {
                             
                     // Show most recent active jobs (up to 5)
                     let active_jobs: Vec<_> = jobs.iter()
-                        .filter(|j| j.status == "processing" || j.status == "queued")
+                        .filter(|j| j.status.is_active())
                         .take(5)
                         .collect();
                     
                     if !active_jobs.is_empty() {
                         println!("\n{} Recent Active Jobs:", "🔄".blue());
                         for job in active_jobs {
-                            let status_icon = if job.status == "processing" { "⚙️".blue() } else { "⏳".yellow() };
+                            let status_icon = if job.status == JobStatus::Processing { "⚙️".blue() } else { "⏳".yellow() };
                             println!(
                                 "   {} {} ({}) {}",
                                 status_icon,
@@ -82,6 +127,10 @@ async fn display_service_info() -> Result<()> {
     println!("   {} {:<12} - View all transcription jobs", "📜".green(), "list-jobs");
     println!("   {} {:<12} - Check status of a specific job", "🔍".green(), "status");
     println!("   {} {:<12} - Cancel a running job", "🛑".green(), "terminate");
+    println!("   {} {:<12} - Wait for a running job and save its transcript", "⏳".green(), "wait");
+    println!("   {} {:<12} - Save the transcript of a completed job", "📥".green(), "fetch");
+    println!("   {} {:<12} - Inspect or clean up the result cache", "🗄️".green(), "cache");
+    println!("   {} {:<12} - Show, change or check the configuration", "⚙️".green(), "config");
     
     // Display supported file formats
     println!("\n{} Supported File Formats:", "🔊".blue());
@@ -94,6 +143,10 @@ async fn display_service_info() -> Result<()> {
     println!("   whisper-client list-jobs");
     println!("   whisper-client status --job-id <ID>");
     println!("   whisper-client terminate --job-id <ID>");
+    println!("   whisper-client wait --job-id <ID>");
+    println!("   whisper-client fetch --job-id <ID> --format srt");
+    println!("   whisper-client cache prune --older-than 30d");
+    println!("   whisper-client config get service_url");
     
     println!("\n{} For detailed help on any command:", "ℹ️".blue());
     println!("   whisper-client <command> --help");
@@ -101,73 +154,591 @@ async fn display_service_info() -> Result<()> {
     Ok(())
 }
 
-async fn process_batch(files: Vec<std::path::PathBuf>, verbose: bool) -> Result<()> {
+/// How `process_batch` should handle the files it is given.
+struct BatchOptions<'a> {
+    formatters: &'a [&'a dyn OutputFormatter],
+    /// Parameters sent with every upload.
+    transcription: &'a TranscriptionOptions,
+    concurrency: usize,
+    verbose: bool,
+    /// Progress record for resuming; `None` for one-off runs such as YouTube downloads.
+    manifest: Option<BatchManifest>,
+    /// Skip completed files and re-attach to in-flight jobs recorded in the manifest.
+    resume: bool,
+    /// Skip files whose outputs are already there.
+    skip: SkipPolicy,
+    /// Only upload, leaving the jobs running on the server.
+    detach: bool,
+}
+
+/// What happened to a file that didn't fail.
+enum Processed {
+    Transcribed,
+    /// Uploaded with `--detach`; the job is still running.
+    Submitted(String),
+    /// Left alone because an earlier run already transcribed it.
+    Skipped,
+}
+
+/// Apply an update to the batch manifest, warning rather than failing if it can't be saved.
+fn update_manifest(
+    manifest: Option<&Mutex<BatchManifest>>,
+    progress: &MultiProgress,
+    update: impl FnOnce(&mut BatchManifest) -> Result<()>,
+) {
+    if let Some(manifest) = manifest {
+        let mut manifest = manifest.lock().unwrap();
+        if let Err(e) = update(&mut manifest) {
+            progress.suspend(|| println!("{} Warning: Failed to update batch manifest: {:#}", "⚠️".yellow(), e));
+        }
+    }
+}
+
+/// Reports cache hits, and records each job a file is submitted as in the batch manifest.
+struct ManifestHooks<'a> {
+    file: &'a Path,
+    label: &'a str,
+    progress: &'a MultiProgress,
+    manifest: Option<&'a Mutex<BatchManifest>>,
+}
+
+impl TranscribeHooks for ManifestHooks<'_> {
+    fn cached(&self) {
+        self.progress.suspend(|| println!("{} Using cached result for {}", "↳".blue(), self.label));
+    }
+
+    fn submitted(&self, job: &JobResponse) {
+        update_manifest(self.manifest, self.progress, |m| m.record_submitted(self.file, &job.job_id));
+    }
+}
+
+/// Transcribe one file and write it in every requested format.
+///
+/// With `reattach`, waits for that existing job instead of uploading the file again.
+async fn transcribe_and_save(
+    client: &WhisperClient,
+    file: &Path,
+    options: &BatchOptions<'_>,
+    progress: &MultiProgress,
+    manifest: Option<&Mutex<BatchManifest>>,
+    reattach: Option<String>,
+) -> Result<Processed> {
+    let label = file
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let hooks = ManifestHooks { file, label: &label, progress, manifest };
+
+    if options.detach {
+        // Nothing to submit if the result is already known
+        if let Some(record) = client.cached_result(file, options.transcription).await? {
+            hooks.cached();
+            save_outputs(file, options, progress, manifest, record.transcription, record.job)?;
+            return Ok(Processed::Transcribed);
+        }
+        let job_id = match reattach {
+            Some(job_id) => job_id,
+            None => {
+                let job = client.submit_file(file, options.transcription, progress).await?;
+                update_manifest(manifest, progress, |m| m.record_submitted(file, &job.job_id));
+                job.job_id
+            }
+        };
+        let endpoint = match client.job_endpoint(&job_id) {
+            Some(endpoint) if client.endpoints().len() > 1 => format!(" on {}", redact_url_password(endpoint)),
+            _ => String::new(),
+        };
+        progress.suspend(|| println!("{} Submitted {} as job {}{}", "✓".green(), label, job_id, endpoint));
+        return Ok(Processed::Submitted(job_id));
+    }
+
+    if let Some(job_id) = &reattach {
+        progress.suspend(|| println!("{} Re-attaching to job {}", "↳".blue(), job_id));
+    }
+    let (transcription, job_info) = client
+        .transcribe_file_with(file, options.transcription, progress, reattach.as_deref(), &hooks)
+        .await?;
+
+    save_outputs(file, options, progress, manifest, transcription, job_info)?;
+    Ok(Processed::Transcribed)
+}
+
+fn save_outputs(
+    file: &Path,
+    options: &BatchOptions<'_>,
+    progress: &MultiProgress,
+    manifest: Option<&Mutex<BatchManifest>>,
+    transcription: TranscriptionResponse,
+    job_info: JobResponse,
+) -> Result<()> {
+    for formatter in options.formatters {
+        let output_path = formatter.save(&transcription, &job_info, file)?;
+        progress.suspend(|| println!("{} Saved transcript to: {}", "✓".green(), output_path.display()));
+    }
+    update_manifest(manifest, progress, |m| m.record_completed(file, &job_info.job_id));
+
+    if options.verbose {
+        progress.suspend(|| println!("{}", transcript_details(&transcription)));
+    }
+
+    Ok(())
+}
+
+/// The transcript text and its segments, for `--verbose` output.
+fn transcript_details(transcription: &TranscriptionResponse) -> String {
+    let mut details = format!("\n{}\n{}\n\n{}\n", "Transcription:".bold(), transcription.text, "Segments:".bold());
+    for segment in &transcription.segments {
+        details.push_str(&format!("{}s -> {}s: {}\n", segment.start, segment.end, segment.text));
+    }
+    details
+}
+
+/// Where to save the outputs of a job that has no local input file: `output_dir` (or the
+/// current directory), named after the original upload or else the job ID.
+fn job_output_base(job: &JobResponse, output_dir: Option<&Path>) -> PathBuf {
+    let output_dir = output_dir
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| std::env::current_dir().unwrap());
+    let source_name = job.filename.as_deref()
+        .and_then(|name| Path::new(name).file_name())
+        .unwrap_or_else(|| job.job_id.as_ref());
+    output_dir.join(source_name)
+}
+
+async fn process_batch(client: &WhisperClient, files: Vec<PathBuf>, mut options: BatchOptions<'_>) -> Result<()> {
     let total = files.len();
     println!("\n{} Found {} files to process", "→".blue(), total);
-    
-    for (index, file) in files.into_iter().enumerate() {
-        println!("\n{} Processing file {} of {}: {}", "→".blue(), index + 1, total, file.display());
-        println!("{} Sending file to Whisper service...", "→".blue());
-        
-        match transcribe_file(&file).await {
-            Ok((transcription, job_info)) => {
-                let output_path = save_markdown_response(&transcription, &file, &job_info)?;
-                println!("{} Saved transcript to: {}", "✓".green(), output_path.display());
+    if options.concurrency > 1 && total > 1 {
+        println!("{} Running up to {} jobs at once", "→".blue(), options.concurrency);
+    }
+    if let Some(manifest) = &options.manifest {
+        println!("{} Recording progress in {}", "→".blue(), manifest.path().display());
+    }
 
-                if verbose {
-                    println!("\n{}", "Transcription:".bold());
-                    println!("{}\n", transcription.text);
+    let progress = MultiProgress::new();
+    let manifest = options.manifest.take().map(Mutex::new);
+    let options = &options;
 
-                    println!("{}", "Segments:".bold());
-                    for segment in transcription.segments {
-                        println!(
-                            "{}s -> {}s: {}",
-                            segment.start, segment.end, segment.text
-                        );
+    // Each file succeeds or fails on its own; one failure doesn't stop the batch
+    let mut results: Vec<(usize, PathBuf, Result<Processed>)> = stream::iter(files.into_iter().enumerate())
+        .map(|(index, file)| {
+            let progress = &progress;
+            let manifest = manifest.as_ref();
+            async move {
+                let outputs: Vec<PathBuf> = options.formatters
+                    .iter()
+                    .filter_map(|formatter| formatter.output_path(&file).ok())
+                    .collect();
+                if should_skip(&file, &outputs, options.skip) {
+                    progress.suspend(|| {
+                        println!("\n{} Skipping file {} of {} (output exists): {}", "↷".blue(), index + 1, total, file.display())
+                    });
+                    return (index, file, Ok(Processed::Skipped));
+                }
+
+                let action = match manifest {
+                    Some(manifest) if options.resume => manifest.lock().unwrap().resume_action(&file),
+                    _ => ResumeAction::Submit,
+                };
+                let reattach = match action {
+                    ResumeAction::Skip => {
+                        progress.suspend(|| {
+                            println!("\n{} Skipping file {} of {} (already transcribed): {}", "↷".blue(), index + 1, total, file.display())
+                        });
+                        return (index, file, Ok(Processed::Skipped));
                     }
-                    println!();
+                    ResumeAction::Reattach(job_id) => Some(job_id),
+                    ResumeAction::Submit => None,
+                };
+
+                progress.suspend(|| {
+                    println!("\n{} Processing file {} of {}: {}", "→".blue(), index + 1, total, file.display())
+                });
+
+                let result = transcribe_and_save(client, &file, options, progress, manifest, reattach).await;
+                if let Err(e) = &result {
+                    progress.suspend(|| println!("{} Error processing {}: {}", "✗".red(), file.display(), e));
+                    update_manifest(manifest, progress, |m| match e.downcast_ref::<WhisperError>() {
+                        Some(error) => m.record_error(&file, error),
+                        None => m.record_failed(&file, None, &e.to_string()),
+                    });
                 }
+                (index, file, result)
             }
+        })
+        .buffer_unordered(options.concurrency.max(1))
+        .collect()
+        .await;
+    results.sort_by_key(|(index, _, _)| *index);
+
+    let skipped = results.iter().filter(|(_, _, result)| matches!(result, Ok(Processed::Skipped))).count();
+    let submitted: Vec<_> = results
+        .iter()
+        .filter_map(|(_, file, result)| match result {
+            Ok(Processed::Submitted(job_id)) => Some((file, job_id)),
+            _ => None,
+        })
+        .collect();
+    let failures: Vec<_> = results
+        .iter()
+        .filter_map(|(_, file, result)| result.as_ref().err().map(|e| (file, e)))
+        .collect();
+
+    println!("\n{} Batch processing complete!", "✓".green());
+    let succeeded = total - failures.len() - skipped - submitted.len();
+    if succeeded > 0 || submitted.is_empty() {
+        println!("   {} {} succeeded", "✓".green(), succeeded);
+    }
+    if !submitted.is_empty() {
+        println!("   {} {} submitted:", "↑".blue(), submitted.len());
+        for (file, job_id) in &submitted {
+            println!("     {} {}", job_id, file.display());
+        }
+        println!(
+            "{} Collect results with: {}",
+            "↳".blue(),
+            "whisper-client wait --job-id <JOB_ID>".bold()
+        );
+    }
+    if skipped > 0 {
+        println!("   {} {} skipped", "↷".blue(), skipped);
+    }
+    if !failures.is_empty() {
+        println!("   {} {} failed:", "✗".red(), failures.len());
+        for (file, e) in &failures {
+            println!("     {} {}", file.display(), e);
+        }
+    }
+
+    // Exit with the code of the first failed file, if any
+    if let Some((_, e)) = failures.first() {
+        let code = e.downcast_ref::<WhisperError>().map(exit_code).unwrap_or(1);
+        std::process::exit(code);
+    }
+    Ok(())
+}
+
+/// The config in effect. Later layers win: file, profile, environment, command line.
+fn load_config(args: &Args, read_only: bool) -> std::result::Result<Config, WhisperError> {
+    let path = args.config_path().map_err(|e| WhisperError::Config(format!("{:#}", e)))?;
+    let config = if read_only { Config::load_read_only_from(&path) } else { Config::load_from(&path) };
+    let mut overrides = Config::env_overrides();
+    overrides.extend(args.config_overrides());
+    let mut config = config
+        .map_err(|e| WhisperError::Config(format!("{:#}", e)))?
+        .with_profile_and_overrides(args.profile.as_deref(), &overrides)?;
+    config.timeouts = args.timeouts(&config.timeouts);
+    config.proxy = args.proxy(&config.proxy);
+    config.transcription = args.transcription_options().or(&config.transcription);
+    Ok(config)
+}
+
+fn build_client(args: &Args) -> std::result::Result<WhisperClient, WhisperError> {
+    let config = load_config(args, args.read_only)?;
+    let mut builder = WhisperClient::builder().config(config);
+    if !args.no_cache {
+        if let Some(dir) = ResultCache::default_dir() {
+            builder = builder.cache(ResultCache::new(dir));
+        }
+    }
+    builder.build()
+}
+
+/// Run a `cache` action: stats, clear or prune.
+fn run_cache_command(action: &str, older_than: Option<&str>) -> Result<()> {
+    let cache = ResultCache::open_default()?;
+    match action {
+        "stats" => {
+            let stats = cache.stats()?;
+            let format_time = |time: Option<std::time::SystemTime>| {
+                time.map(|t| chrono::DateTime::<chrono::Local>::from(t).format("%Y-%m-%d %H:%M:%S").to_string())
+                    .unwrap_or_else(|| "-".to_string())
+            };
+            println!("\n{}", "Result Cache:".bold());
+            println!("   Directory: {}", cache.dir().display());
+            println!("   Entries:   {}", stats.entries);
+            println!("   Size:      {} bytes", stats.total_bytes);
+            println!("   Oldest:    {}", format_time(stats.oldest));
+            println!("   Newest:    {}", format_time(stats.newest));
+        }
+        "clear" => {
+            let removed = cache.clear()?;
+            println!("{} Removed {} cached results", "✓".green(), removed);
+        }
+        "prune" => {
+            let Some(older_than) = older_than else {
+                println!("{} Error: Missing required --older-than argument for cache prune", "✗".red());
+                println!("{} Usage: whisper-client cache prune --older-than 30d", "ℹ️".blue());
+                std::process::exit(1);
+            };
+            let removed = cache.prune(parse_age(older_than)?)?;
+            println!("{} Removed {} cached results older than {}", "✓".green(), removed, older_than);
+        }
+        other => {
+            println!("{} Error: Unknown cache action '{}'", "✗".red(), other);
+            println!("{} Usage: whisper-client cache <stats|clear|prune>", "ℹ️".blue());
+            std::process::exit(1);
+        }
+    }
+    Ok(())
+}
+
+/// Print `e` and exit with its code.
+fn exit_with(e: WhisperError) -> ! {
+    println!("{} Error: {}", "✗".red(), e);
+    std::process::exit(exit_code(&e));
+}
+
+/// Ask `question` on the terminal. An empty answer means `default`.
+fn prompt(question: &str, default: Option<&str>) -> Result<String> {
+    match default {
+        Some(default) if !default.is_empty() => print!("{} [{}]: ", question, default),
+        _ => print!("{}: ", question),
+    }
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    if std::io::stdin().read_line(&mut answer)? == 0 {
+        anyhow::bail!("Input ended before the setup was complete");
+    }
+    let answer = answer.trim();
+    Ok(if answer.is_empty() { default.unwrap_or_default().to_string() } else { answer.to_string() })
+}
+
+/// Walk through setting the service URL and transcription defaults, checking that the
+/// service answers before saving.
+async fn init_config(file: &mut ConfigFile) -> Result<()> {
+    let current = file.config().unwrap_or_default();
+    println!("\n{} Setting up {}", "⚙️".blue(), file.path().display());
+
+    loop {
+        let url = prompt("Service URL", Some(&current.service_url))?;
+        if let Err(e) = reqwest::Url::parse(&url) {
+            println!("{} Invalid URL: {}", "✗".red(), e);
+            continue;
+        }
+        file.set("service_url", &url)?;
+        let checked = match file.config().and_then(WhisperClient::new) {
+            Ok(client) => client.check_service().await,
+            Err(e) => Err(e),
+        };
+        match checked {
+            Ok(()) => println!("{} Service is reachable", "✓".green()),
             Err(e) => {
-                println!("\n{} Error processing {}: {}", "✗".red(), file.display(), e);
+                println!("{} {}", "✗".red(), e);
+                if !prompt("Use this URL anyway? (y/N)", None)?.eq_ignore_ascii_case("y") {
+                    continue;
+                }
+            }
+        }
+        break;
+    }
+
+    let language = prompt(
+        "Default language, e.g. en (blank to let the service detect it)",
+        current.transcription.language.as_deref(),
+    )?;
+    file.set("transcription.language", &language)?;
+    let model = prompt("Default model, e.g. large-v3 (blank for the service default)", current.transcription.model.as_deref())?;
+    file.set("transcription.model", &model)?;
+
+    file.save()?;
+    println!("{} Saved {}", "✓".green(), file.path().display());
+    Ok(())
+}
+
+/// Check the config file, then every service instance it names. Exits with code 11 for
+/// problems in the file, or the connection error's code if an instance is down.
+async fn validate_config(path: &Path) -> Result<()> {
+    if !path.exists() {
+        println!("{} No config file at {}; the defaults apply", "ℹ️".blue(), path.display());
+    }
+    let file = open_config_file(path);
+    let problems = file.problems();
+    for problem in &problems {
+        println!("{} {}", "✗".red(), problem);
+    }
+    let Ok(config) = file.config() else {
+        std::process::exit(11);
+    };
+
+    // The top-level settings, then each profile
+    let mut configs = vec![Ok(config.clone())];
+    configs.extend(config.profiles.keys().map(|name| config.clone().with_profile(Some(name))));
+    let mut checked = HashSet::new();
+    let mut unreachable = None;
+    for client in configs.into_iter().map(|config| config.and_then(WhisperClient::new)) {
+        let client = match client {
+            Ok(client) => client,
+            Err(e) => exit_with(e),
+        };
+        for load in client.endpoint_loads().await {
+            if !checked.insert(load.url.clone()) {
                 continue;
             }
+            match load.active_jobs {
+                Ok(_) => println!("{} {} is reachable", "✓".green(), redact_url_password(&load.url)),
+                Err(e) => {
+                    println!("{} {}", "✗".red(), e);
+                    unreachable.get_or_insert(e);
+                }
+            }
+        }
+    }
+
+    if !problems.is_empty() {
+        std::process::exit(11);
+    }
+    if let Some(e) = unreachable {
+        std::process::exit(exit_code(&e));
+    }
+    println!("{} {} is valid", "✓".green(), path.display());
+    Ok(())
+}
+
+fn open_config_file(path: &Path) -> ConfigFile {
+    ConfigFile::open(path).unwrap_or_else(|e| exit_with(WhisperError::Config(format!("{:#}", e))))
+}
+
+/// Run a `config` action: path, dirs, get, set, init or validate.
+async fn run_config_command(args: &Args) -> Result<()> {
+    let action = args.path.as_deref().and_then(Path::to_str).unwrap_or("path");
+    let path = args.config_path()?;
+    match (action, args.config_args.as_slice()) {
+        ("path", []) => println!("{}", path.display()),
+        ("dirs", []) => {
+            let show = |dir: Option<PathBuf>| dir.map_or_else(|| "-".to_string(), |dir| dir.display().to_string());
+            println!("\n{}", "Directories:".bold());
+            println!("   Config file: {}", path.display());
+            println!("   Cache:       {}", show(cache_dir()));
+            println!("   State:       {}", show(state_dir()));
+        }
+        ("get", [key]) => {
+            // Reading a setting shouldn't create the file
+            let value = load_config(args, true).and_then(|config| config.get(key)).unwrap_or_else(|e| exit_with(e));
+            match value {
+                Some(serde_json::Value::String(text)) => println!("{}", text),
+                Some(value) if value.is_object() => println!("{}", serde_json::to_string_pretty(&value)?),
+                Some(value) => println!("{}", value),
+                // Unset, like `git config --get`
+                None => std::process::exit(1),
+            }
+        }
+        ("set", [key, value]) => {
+            let mut file = open_config_file(&path);
+            file.set(key, value).unwrap_or_else(|e| exit_with(e));
+            file.save()?;
+            println!("{} Set {} in {}", "✓".green(), key, path.display());
+        }
+        ("init", []) => init_config(&mut open_config_file(&path)).await?,
+        ("validate", []) => validate_config(&path).await?,
+        _ => {
+            println!("{} Error: Invalid arguments for config {}", "✗".red(), action);
+            println!(
+                "{} Usage: whisper-client config path | dirs | get <KEY> | set <KEY> <VALUE> | init | validate",
+                "ℹ️".blue()
+            );
+            std::process::exit(1);
         }
     }
-    
-    println!("\n{} Batch processing complete!", "✓".green());
     Ok(())
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let args = Args::parse();
+    if args.config_file.is_none() && !args.read_only {
+        match migrate_legacy_config() {
+            Ok(Some((from, to))) => {
+                eprintln!("{} Moved the config file from {} to {}", "ℹ️".blue(), from.display(), to.display())
+            }
+            Ok(None) => {}
+            Err(e) => eprintln!("Warning: Failed to move the config file to its new location: {:#}", e),
+        }
+    }
+    if matches!(args.command, Some(Command::Config)) {
+        // Runs before the client is built, so a broken config can still be inspected and fixed
+        return run_config_command(&args).await;
+    }
+    if let Some(extra) = args.config_args.first() {
+        println!("{} Error: Unexpected argument: {}", "✗".red(), extra);
+        std::process::exit(1);
+    }
+    let client = match build_client(&args) {
+        Ok(client) => client,
+        Err(e) => {
+            println!("{} Error: {}", "✗".red(), e);
+            std::process::exit(exit_code(&e));
+        }
+    };
 
     println!("\n{} {}", "🎤".blue(), "Whisper Transcription".bold());
+    if client.config().tls.danger_accept_invalid_certs {
+        println!("{} Warning: TLS certificate verification is disabled (tls.danger_accept_invalid_certs)", "⚠️".yellow());
+    }
 
     // Check if service is running for commands that need it
     let needs_service_check = match args.command {
         Some(Command::Info) => false, // Info command handles service check internally
+        Some(Command::Cache) => false, // Purely local
         None => false, // Default to Info command
         _ => true, // All other commands need service check
     };
 
     if needs_service_check {
-        if let Err(e) = check_service().await {
+        if let Err(e) = client.check_service().await {
             println!("{} Error: {}", "✗".red(), e);
-            println!(
-                "{} Start the service with: {}",
-                "↳".blue(),
-                "docker compose up -d".bold()
-            );
-            std::process::exit(1);
+            if matches!(e, WhisperError::Proxy { .. }) {
+                let host = reqwest::Url::parse(client.service_url())
+                    .ok()
+                    .and_then(|url| url.host_str().map(str::to_string))
+                    .unwrap_or_else(|| "<HOST>".to_string());
+                println!(
+                    "{} Check the proxy settings, or bypass the proxy with {}",
+                    "↳".blue(),
+                    format!("--no-proxy {}", host).bold()
+                );
+            } else {
+                println!(
+                    "{} Start the service with: {}",
+                    "↳".blue(),
+                    "docker compose up -d".bold()
+                );
+            }
+            std::process::exit(exit_code(&e));
         }
     }
 
+    let skip_policy = args.skip_policy();
+    let transcription_options = args.transcription_options().or(&client.config().transcription);
+
+    let mut registry = FormatterRegistry::with_builtins();
+    if let Some(cue_settings) = &args.vtt_cue_settings {
+        registry.register(VttFormatter::with_cue_settings(cue_settings.clone()));
+    }
+    let formatters = match registry.select(&args.formats) {
+        Ok(formatters) => formatters,
+        Err(e) => {
+            println!("{} Error: {}", "✗".red(), e);
+            std::process::exit(1);
+        }
+    };
+    // Transcribing always writes something; markdown unless told otherwise
+    let transcribe_formatters = if formatters.is_empty() {
+        registry.select(&["md".to_string()])?
+    } else {
+        formatters.clone()
+    };
+
     match args.command.unwrap_or(Command::Info) {
         Command::Info => {
-            display_service_info().await?;
+            display_service_info(&client).await?;
         },
+        Command::Cache => {
+            let action = args.path.as_deref().and_then(Path::to_str).unwrap_or("stats");
+            run_cache_command(action, args.older_than.as_deref())?;
+        }
+        Command::Config => unreachable!("config commands run before the client is built"),
         Command::Transcribe => {
             // Validate required arguments
             if args.path.is_none() {
@@ -186,7 +757,24 @@ async fn main() -> Result<()> {
                 std::process::exit(1);
             }
             
-            process_batch(files, args.verbose).await?;
+            // One manifest per directory, the one holding the outputs
+            let input_dir = if path.is_dir() {
+                path.clone()
+            } else {
+                path.parent().map(Path::to_path_buf).unwrap_or_default()
+            };
+            let manifest = BatchManifest::load_default(&input_dir)?;
+
+            process_batch(&client, files, BatchOptions {
+                formatters: &transcribe_formatters,
+                transcription: &transcription_options,
+                concurrency: args.concurrency.into(),
+                verbose: args.verbose,
+                manifest: Some(manifest),
+                resume: args.resume,
+                skip: skip_policy,
+                detach: args.detach,
+            }).await?;
         }
         Command::TranscribeYoutube => {
             // Validate required arguments
@@ -208,18 +796,23 @@ async fn main() -> Result<()> {
             let audio_file = youtube::convert_to_audio(&video_path).expect("Failed to convert video to audio");
             
             // Use existing transcription flow
-            process_batch(vec![audio_file], args.verbose).await?;
+            process_batch(&client, vec![audio_file], BatchOptions {
+                formatters: &transcribe_formatters,
+                transcription: &transcription_options,
+                concurrency: 1,
+                verbose: args.verbose,
+                manifest: None,
+                resume: false,
+                skip: SkipPolicy::Never,
+                detach: args.detach,
+            }).await?;
         }
         Command::ListJobs => {
-            match list_jobs().await {
+            match client.list_jobs().await {
                 Ok(jobs) => {
                     println!("\n{}", "Jobs:".bold());
                     for job in jobs {
-                        let status_color = match job.status.as_str() {
-                            "completed" => "✓".green(),
-                            "failed" => "✗".red(),
-                            _ => "⋯".blue(),
-                        };
+                        let status_color = status_marker(&job.status);
                         
                         println!(
                             "{} {} - {} {}",
@@ -245,7 +838,7 @@ async fn main() -> Result<()> {
                 }
                 Err(e) => {
                     println!("\n{} Error: {}", "✗".red(), e);
-                    std::process::exit(1);
+                    std::process::exit(exit_code(&e));
                 }
             }
         }
@@ -258,17 +851,13 @@ async fn main() -> Result<()> {
             }
             
             let job_id = args.job_id.unwrap();
-            match get_job_status(&job_id, true).await {
+            match client.get_job_status(&job_id, true).await {
                 Ok(job) => {
-                    let status_color = match job.status.as_str() {
-                        "completed" => "✓".green(),
-                        "failed" => "✗".red(),
-                        _ => "⋯".blue(),
-                    };
+                    let status_color = status_marker(&job.status);
                     
                     println!("\n{} Status for job {}:", status_color, job.job_id);
                     println!("Status: {}", job.status);
-                    if let Some(filename) = job.filename {
+                    if let Some(filename) = &job.filename {
                         println!("File: {}", filename);
                     }
                     if let Some(created_at) = job.created_at {
@@ -281,7 +870,15 @@ async fn main() -> Result<()> {
                         println!("Message: {}", job.message);
                     }
                     
-                    if args.verbose && job.status == "completed" {
+                    if let Some(result) = job.result.as_ref().filter(|_| !formatters.is_empty()) {
+                        let output_base = job_output_base(&job, args.output_dir.as_deref());
+                        for formatter in &formatters {
+                            let output_path = formatter.save(result, &job, &output_base)?;
+                            println!("{} Saved transcript to: {}", "✓".green(), output_path.display());
+                        }
+                    }
+
+                    if args.verbose && job.status == JobStatus::Completed {
                         if let Some(result) = job.result {
                             println!("\n{}", "Transcription:".bold());
                             println!("{}\n", result.text);
@@ -298,8 +895,110 @@ async fn main() -> Result<()> {
                 }
                 Err(e) => {
                     println!("\n{} Error: {}", "✗".red(), e);
+                    std::process::exit(exit_code(&e));
+                }
+            }
+        }
+        Command::Wait => {
+            let Some(job_id) = args.job_id else {
+                println!("{} Error: Missing required --job-id argument for wait command", "✗".red());
+                println!("{} Usage: whisper-client wait --job-id <JOB_ID>", "ℹ️".blue());
+                std::process::exit(1);
+            };
+
+            println!("\n{} Waiting for job {}", "→".blue(), job_id);
+            let progress = MultiProgress::new();
+            let (transcription, job) = match client.attach_to_job_with_progress(&job_id, &job_id, &progress).await {
+                Ok(result) => result,
+                Err(e) => {
+                    println!("{} Error: {}", "✗".red(), e);
+                    if matches!(e, WhisperError::Interrupted { .. }) {
+                        println!("{} Re-attach with: {}", "↳".blue(), format!("whisper-client wait --job-id {}", job_id).bold());
+                    }
+                    std::process::exit(exit_code(&e));
+                }
+            };
+
+            let output_base = job_output_base(&job, args.output_dir.as_deref());
+            for formatter in &transcribe_formatters {
+                let output_path = formatter.save(&transcription, &job, &output_base)?;
+                println!("{} Saved transcript to: {}", "✓".green(), output_path.display());
+            }
+            if args.verbose {
+                println!("{}", transcript_details(&transcription));
+            }
+        }
+        Command::Fetch => {
+            let Some(job_id) = args.job_id else {
+                println!("{} Error: Missing required --job-id argument for fetch command", "✗".red());
+                println!("{} Usage: whisper-client fetch --job-id <JOB_ID> [--out FILE] [--format FORMAT]", "ℹ️".blue());
+                std::process::exit(1);
+            };
+
+            let job = match client.get_job_status(&job_id, true).await {
+                Ok(job) => job,
+                Err(e) => {
+                    println!("{} Error: {}", "✗".red(), e);
+                    std::process::exit(exit_code(&e));
+                }
+            };
+            let not_ready = match &job.status {
+                JobStatus::Completed => None,
+                JobStatus::Failed => Some(WhisperError::JobFailed { job_id: job_id.clone(), message: job.message.clone() }),
+                JobStatus::Terminated | JobStatus::Cancelled => {
+                    Some(WhisperError::JobTerminated { job_id: job_id.clone(), message: job.message.clone() })
+                }
+                status => {
+                    println!("{} Job {} is still {}", "✗".red(), job_id, status);
+                    println!("{} Wait for it with: whisper-client wait --job-id {}", "ℹ️".blue(), job_id);
                     std::process::exit(1);
                 }
+            };
+            if let Some(e) = not_ready {
+                println!("{} Error: {}", "✗".red(), e);
+                std::process::exit(exit_code(&e));
+            }
+            let Some(transcription) = job.result.clone() else {
+                let e = WhisperError::MalformedResponse(format!("job {} completed without a result", job_id));
+                println!("{} Error: {}", "✗".red(), e);
+                std::process::exit(exit_code(&e));
+            };
+
+            match &args.out {
+                Some(out) => {
+                    // One file: the format comes from --format or else the file extension
+                    let formatter = match formatters.as_slice() {
+                        [formatter] => *formatter,
+                        [] => {
+                            let extension = out.extension().and_then(|ext| ext.to_str()).unwrap_or("md");
+                            match registry.get(extension) {
+                                Some(formatter) => formatter,
+                                None => {
+                                    println!("{} Error: Cannot tell the output format from {}; pass --format", "✗".red(), out.display());
+                                    std::process::exit(1);
+                                }
+                            }
+                        }
+                        _ => {
+                            println!("{} Error: --out takes a single --format", "✗".red());
+                            std::process::exit(1);
+                        }
+                    };
+                    let content = formatter.format(&transcription, &job, out)?;
+                    std::fs::write(out, content)
+                        .with_context(|| format!("Failed to write {}", out.display()))?;
+                    println!("{} Saved transcript to: {}", "✓".green(), out.display());
+                }
+                None => {
+                    let output_base = job_output_base(&job, args.output_dir.as_deref());
+                    for formatter in &transcribe_formatters {
+                        let output_path = formatter.save(&transcription, &job, &output_base)?;
+                        println!("{} Saved transcript to: {}", "✓".green(), output_path.display());
+                    }
+                }
+            }
+            if args.verbose {
+                println!("{}", transcript_details(&transcription));
             }
         }
         Command::Terminate => {
@@ -313,7 +1012,7 @@ async fn main() -> Result<()> {
             let job_id = args.job_id.unwrap();
             println!("\n{} Attempting to terminate job {}...", "→".blue(), job_id);
             
-            match terminate_job(&job_id).await {
+            match client.terminate_job(&job_id).await {
                 Ok(job) => {
                     println!("{} Job terminated successfully", "✓".green());
                     println!("Status: {}", job.status);
@@ -323,7 +1022,7 @@ async fn main() -> Result<()> {
                 }
                 Err(e) => {
                     println!("{} Error terminating job: {}", "✗".red(), e);
-                    std::process::exit(1);
+                    std::process::exit(exit_code(&e));
                 }
             }
         }
@@ -0,0 +1,250 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use crate::error::WhisperError;
+
+/// Where a file in a batch run got to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileOutcome {
+    /// Uploaded; the job may still be running on the server.
+    Submitted,
+    Completed,
+    Failed,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub size: u64,
+    /// Modification time in seconds since the Unix epoch.
+    pub mtime: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub job_id: Option<String>,
+    pub outcome: FileOutcome,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// What a resumed batch run should do with a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResumeAction {
+    /// Already transcribed and unchanged since.
+    Skip,
+    /// Still in flight on the server; wait for this job instead of uploading again.
+    Reattach(String),
+    /// Not started, failed, or changed since: upload it.
+    Submit,
+}
+
+/// Record of a batch run, so an interrupted run can be resumed.
+///
+/// There is one per directory transcribed, kept in the state directory. Every update is
+/// written to disk straight away.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct BatchManifest {
+    #[serde(skip)]
+    path: PathBuf,
+    /// The directory being transcribed, as given.
+    #[serde(skip)]
+    dir: PathBuf,
+    /// The directory being transcribed, for anyone looking through the state directory.
+    #[serde(default)]
+    pub directory: PathBuf,
+    /// Entries keyed by input path, relative to the directory being transcribed where possible.
+    pub files: BTreeMap<String, ManifestEntry>,
+}
+
+impl BatchManifest {
+    /// Where manifests are kept: `manifests` in the platform state directory.
+    pub fn default_dir() -> Option<PathBuf> {
+        crate::paths::state_dir().map(|dir| dir.join("manifests"))
+    }
+
+    /// Load the manifest for transcribing `dir` from [`default_dir`](Self::default_dir).
+    pub fn load_default(dir: &Path) -> Result<Self> {
+        let manifest_dir = Self::default_dir().context("Could not determine state directory")?;
+        Self::load_or_default(dir, &manifest_dir)
+    }
+
+    /// Load the manifest for transcribing `dir` from `manifest_dir`, or start an empty one
+    /// if there is none.
+    pub fn load_or_default(dir: &Path, manifest_dir: &Path) -> Result<Self> {
+        let dir = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+        let directory = std::fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf());
+        let hash = Sha256::digest(directory.to_string_lossy().as_bytes());
+        let name: String = hash.iter().take(8).map(|byte| format!("{:02x}", byte)).collect();
+        let path = manifest_dir.join(format!("{}.json", name));
+
+        let mut manifest = if path.exists() {
+            let content = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read manifest {}", path.display()))?;
+            serde_json::from_str::<BatchManifest>(&content)
+                .with_context(|| format!("Failed to parse manifest {}", path.display()))?
+        } else {
+            BatchManifest::default()
+        };
+        manifest.path = path;
+        manifest.dir = dir.to_path_buf();
+        manifest.directory = directory;
+        Ok(manifest)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn entry(&self, file: &Path) -> Option<&ManifestEntry> {
+        self.files.get(&self.key(file))
+    }
+
+    pub fn resume_action(&self, file: &Path) -> ResumeAction {
+        let entry = match self.entry(file) {
+            Some(entry) => entry,
+            None => return ResumeAction::Submit,
+        };
+        // A file that changed since it was recorded has to be transcribed again
+        match file_stamp(file) {
+            Ok((size, mtime)) if size == entry.size && mtime == entry.mtime => {}
+            _ => return ResumeAction::Submit,
+        }
+        match (&entry.outcome, &entry.job_id) {
+            (FileOutcome::Completed, _) => ResumeAction::Skip,
+            (FileOutcome::Submitted, Some(job_id)) => ResumeAction::Reattach(job_id.clone()),
+            _ => ResumeAction::Submit,
+        }
+    }
+
+    pub fn record_submitted(&mut self, file: &Path, job_id: &str) -> Result<()> {
+        self.record(file, Some(job_id.to_string()), FileOutcome::Submitted, None)
+    }
+
+    pub fn record_completed(&mut self, file: &Path, job_id: &str) -> Result<()> {
+        self.record(file, Some(job_id.to_string()), FileOutcome::Completed, None)
+    }
+
+    pub fn record_failed(&mut self, file: &Path, job_id: Option<&str>, error: &str) -> Result<()> {
+        self.record(file, job_id.map(str::to_string), FileOutcome::Failed, Some(error.to_string()))
+    }
+
+    /// Record how `file` failed. A job that was left running stays `Submitted`, so a
+    /// resumed run re-attaches to it instead of uploading the file again.
+    pub fn record_error(&mut self, file: &Path, error: &WhisperError) -> Result<()> {
+        match error {
+            WhisperError::Interrupted { job_id } | WhisperError::WaitTimeout { job_id, .. } => {
+                self.record_submitted(file, job_id)
+            }
+            WhisperError::JobFailed { job_id, .. } | WhisperError::JobTerminated { job_id, .. } => {
+                self.record_failed(file, Some(job_id), &error.to_string())
+            }
+            _ => self.record_failed(file, None, &error.to_string()),
+        }
+    }
+
+    fn record(
+        &mut self,
+        file: &Path,
+        job_id: Option<String>,
+        outcome: FileOutcome,
+        error: Option<String>,
+    ) -> Result<()> {
+        let (size, mtime) = file_stamp(file)
+            .with_context(|| format!("Failed to read metadata of {}", file.display()))?;
+        let key = self.key(file);
+        self.files.insert(key, ManifestEntry { size, mtime, job_id, outcome, error });
+        self.save()
+    }
+
+    /// Write the manifest, replacing the old file atomically.
+    pub fn save(&self) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        let tmp_path = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp_path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write manifest {}", tmp_path.display()))?;
+        std::fs::rename(&tmp_path, &self.path)
+            .with_context(|| format!("Failed to write manifest {}", self.path.display()))?;
+        Ok(())
+    }
+
+    fn key(&self, file: &Path) -> String {
+        file.strip_prefix(&self.dir).unwrap_or(file).to_string_lossy().into_owned()
+    }
+}
+
+fn file_stamp(file: &Path) -> std::io::Result<(u64, u64)> {
+    let metadata = std::fs::metadata(file)?;
+    let mtime = metadata
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    Ok((metadata.len(), mtime))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_resume_actions() {
+        let temp_dir = tempdir().unwrap();
+        let dir = temp_dir.path();
+        let done = dir.join("done.mp3");
+        let running = dir.join("running.mp3");
+        let failed = dir.join("failed.mp3");
+        let new = dir.join("new.mp3");
+        for file in [&done, &running, &failed, &new] {
+            std::fs::write(file, "dummy").unwrap();
+        }
+
+        let state_dir = tempdir().unwrap();
+        let mut manifest = BatchManifest::load_or_default(dir, state_dir.path()).unwrap();
+        manifest.record_completed(&done, "job-1").unwrap();
+        manifest.record_submitted(&running, "job-2").unwrap();
+        manifest.record_failed(&failed, Some("job-3"), "boom").unwrap();
+
+        // Reload from disk to make sure every update was saved
+        let manifest = BatchManifest::load_or_default(dir, state_dir.path()).unwrap();
+        assert!(manifest.path().starts_with(state_dir.path()));
+        assert!(manifest.files.contains_key("done.mp3"));
+        assert_eq!(manifest.resume_action(&done), ResumeAction::Skip);
+        assert_eq!(manifest.resume_action(&running), ResumeAction::Reattach("job-2".to_string()));
+        assert_eq!(manifest.resume_action(&failed), ResumeAction::Submit);
+        assert_eq!(manifest.resume_action(&new), ResumeAction::Submit);
+
+        // A changed file is transcribed again
+        std::fs::write(&done, "different content").unwrap();
+        assert_eq!(manifest.resume_action(&done), ResumeAction::Submit);
+    }
+
+    #[test]
+    fn test_jobs_left_running_are_reattached() {
+        let temp_dir = tempdir().unwrap();
+        let dir = temp_dir.path();
+        let interrupted = dir.join("interrupted.mp3");
+        let timed_out = dir.join("timed_out.mp3");
+        let failed = dir.join("failed.mp3");
+        for file in [&interrupted, &timed_out, &failed] {
+            std::fs::write(file, "dummy").unwrap();
+        }
+
+        let state_dir = tempdir().unwrap();
+        let mut manifest = BatchManifest::load_or_default(dir, state_dir.path()).unwrap();
+        let error = WhisperError::Interrupted { job_id: "job-1".to_string() };
+        manifest.record_error(&interrupted, &error).unwrap();
+        let error = WhisperError::WaitTimeout { job_id: "job-2".to_string(), waited: std::time::Duration::from_secs(5) };
+        manifest.record_error(&timed_out, &error).unwrap();
+        let error = WhisperError::JobFailed { job_id: "job-3".to_string(), message: "boom".to_string() };
+        manifest.record_error(&failed, &error).unwrap();
+
+        assert_eq!(manifest.resume_action(&interrupted), ResumeAction::Reattach("job-1".to_string()));
+        assert_eq!(manifest.resume_action(&timed_out), ResumeAction::Reattach("job-2".to_string()));
+        assert_eq!(manifest.resume_action(&failed), ResumeAction::Submit);
+        assert_eq!(manifest.entry(&failed).unwrap().job_id.as_deref(), Some("job-3"));
+    }
+}
@@ -1,4 +1,76 @@
 use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// State of a job on the whisper-service.
+///
+/// Statuses this client doesn't know about are kept verbatim in `Unknown`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(from = "String", into = "String")]
+pub enum JobStatus {
+    Queued,
+    Processing,
+    Completed,
+    Failed,
+    Terminated,
+    Cancelled,
+    Unknown(String),
+}
+
+impl JobStatus {
+    pub fn as_str(&self) -> &str {
+        match self {
+            JobStatus::Queued => "queued",
+            JobStatus::Processing => "processing",
+            JobStatus::Completed => "completed",
+            JobStatus::Failed => "failed",
+            JobStatus::Terminated => "terminated",
+            JobStatus::Cancelled => "cancelled",
+            JobStatus::Unknown(status) => status,
+        }
+    }
+
+    /// The job has finished and will not change state again.
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            JobStatus::Completed | JobStatus::Failed | JobStatus::Terminated | JobStatus::Cancelled
+        )
+    }
+
+    /// The job is waiting for or using the transcription worker.
+    pub fn is_active(&self) -> bool {
+        matches!(self, JobStatus::Queued | JobStatus::Processing)
+    }
+}
+
+impl From<String> for JobStatus {
+    fn from(status: String) -> Self {
+        match status.as_str() {
+            "queued" => JobStatus::Queued,
+            "processing" => JobStatus::Processing,
+            "completed" => JobStatus::Completed,
+            "failed" => JobStatus::Failed,
+            "terminated" => JobStatus::Terminated,
+            "cancelled" => JobStatus::Cancelled,
+            _ => JobStatus::Unknown(status),
+        }
+    }
+}
+
+impl From<JobStatus> for String {
+    fn from(status: JobStatus) -> Self {
+        match status {
+            JobStatus::Unknown(status) => status,
+            known => known.as_str().to_string(),
+        }
+    }
+}
+
+impl fmt::Display for JobStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Progress {
@@ -21,12 +93,18 @@ pub struct Segment {
     pub avg_logprob: f64,
     pub compression_ratio: f64,
     pub no_speech_prob: f64,
+    /// Fields this client doesn't model (e.g. word timings), kept so they survive a round trip.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct TranscriptionResponse {
     pub text: String,
     pub segments: Vec<Segment>,
+    /// Fields this client doesn't model (e.g. detected language), kept so they survive a round trip.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -38,7 +116,7 @@ pub struct FileInfo {
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct JobResponse {
     pub job_id: String,
-    pub status: String,
+    pub status: JobStatus,
     #[serde(default)]
     pub message: String,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -52,3 +130,154 @@ pub struct JobResponse {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub progress: Option<Progress>,
 }
+
+/// A transcript together with the job it came from, as written by the JSON output format.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct TranscriptRecord {
+    /// The job envelope, without the result (that is in `transcription`).
+    pub job: JobResponse,
+    pub transcription: TranscriptionResponse,
+}
+
+impl TranscriptRecord {
+    pub fn new(transcription: TranscriptionResponse, job: &JobResponse) -> Self {
+        let job = JobResponse { result: None, ..job.clone() };
+        Self { job, transcription }
+    }
+}
+
+/// Whether the service should transcribe speech as spoken or translate it to English.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Task {
+    Transcribe,
+    Translate,
+}
+
+impl Task {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Task::Transcribe => "transcribe",
+            Task::Translate => "translate",
+        }
+    }
+}
+
+/// Parameters sent along with an upload. Unset fields are left to the service.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TranscriptionOptions {
+    /// Spoken language as an ISO 639-1 code, e.g. `en`. Detected when unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    /// Model size, e.g. `base` or `large-v3`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub task: Option<Task>,
+    /// Text to prime the model with, such as names and domain vocabulary.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub initial_prompt: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub word_timestamps: Option<bool>,
+}
+
+impl TranscriptionOptions {
+    /// These options, with any unset field taken from `defaults`.
+    pub fn or(self, defaults: &TranscriptionOptions) -> Self {
+        Self {
+            language: self.language.or_else(|| defaults.language.clone()),
+            model: self.model.or_else(|| defaults.model.clone()),
+            task: self.task.or(defaults.task),
+            initial_prompt: self.initial_prompt.or_else(|| defaults.initial_prompt.clone()),
+            temperature: self.temperature.or(defaults.temperature),
+            word_timestamps: self.word_timestamps.or(defaults.word_timestamps),
+        }
+    }
+
+    /// The set options as multipart form fields, in a fixed order.
+    pub fn form_fields(&self) -> Vec<(&'static str, String)> {
+        let mut fields = Vec::new();
+        if let Some(language) = &self.language {
+            fields.push(("language", language.clone()));
+        }
+        if let Some(model) = &self.model {
+            fields.push(("model", model.clone()));
+        }
+        if let Some(task) = self.task {
+            fields.push(("task", task.as_str().to_string()));
+        }
+        if let Some(initial_prompt) = &self.initial_prompt {
+            fields.push(("initial_prompt", initial_prompt.clone()));
+        }
+        if let Some(temperature) = self.temperature {
+            fields.push(("temperature", temperature.to_string()));
+        }
+        if let Some(word_timestamps) = self.word_timestamps {
+            fields.push(("word_timestamps", word_timestamps.to_string()));
+        }
+        fields
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_job_status_round_trip() {
+        for name in ["queued", "processing", "completed", "failed", "terminated", "cancelled", "paused"] {
+            let status: JobStatus = serde_json::from_str(&format!("\"{}\"", name)).unwrap();
+            assert_eq!(status.as_str(), name);
+            assert_eq!(serde_json::to_string(&status).unwrap(), format!("\"{}\"", name));
+        }
+        assert_eq!(JobStatus::from("paused".to_string()), JobStatus::Unknown("paused".to_string()));
+    }
+
+    #[test]
+    fn test_job_status_helpers() {
+        assert!(JobStatus::Queued.is_active());
+        assert!(JobStatus::Processing.is_active());
+        assert!(!JobStatus::Completed.is_active());
+        assert!(JobStatus::Completed.is_terminal());
+        assert!(JobStatus::Cancelled.is_terminal());
+        assert!(!JobStatus::Processing.is_terminal());
+        let unknown = JobStatus::Unknown("paused".to_string());
+        assert!(!unknown.is_active() && !unknown.is_terminal());
+    }
+
+    #[test]
+    fn test_transcription_options() {
+        let defaults = TranscriptionOptions {
+            language: Some("de".to_string()),
+            model: Some("base".to_string()),
+            word_timestamps: Some(false),
+            ..Default::default()
+        };
+        let options = TranscriptionOptions {
+            language: Some("en".to_string()),
+            task: Some(Task::Translate),
+            temperature: Some(0.2),
+            ..Default::default()
+        }
+        .or(&defaults);
+
+        assert_eq!(
+            options.form_fields(),
+            vec![
+                ("language", "en".to_string()),
+                ("model", "base".to_string()),
+                ("task", "translate".to_string()),
+                ("temperature", "0.2".to_string()),
+                ("word_timestamps", "false".to_string()),
+            ]
+        );
+        assert!(TranscriptionOptions::default().form_fields().is_empty());
+
+        let parsed: TranscriptionOptions = serde_json::from_str(r#"{"task": "translate"}"#).unwrap();
+        assert_eq!(parsed.task, Some(Task::Translate));
+        assert_eq!(parsed.language, None);
+    }
+}
@@ -0,0 +1,455 @@
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use crate::models::{JobResponse, Segment, TranscriptRecord, TranscriptionResponse};
+
+/// Renders a finished transcription into one output file format.
+///
+/// Implement this to add formats of your own and register them with a
+/// [`FormatterRegistry`].
+pub trait OutputFormatter: Send + Sync {
+    /// Name used to select the formatter, e.g. with `--format`.
+    fn name(&self) -> &str;
+
+    /// Extension of the files written, without the leading dot.
+    fn extension(&self) -> &str;
+
+    /// Render the transcript of `input_path`, the audio file that was transcribed.
+    fn format(
+        &self,
+        response: &TranscriptionResponse,
+        job_info: &JobResponse,
+        input_path: &Path,
+    ) -> Result<String>;
+
+    /// Where to write the output for `input_path`. Defaults to `<stem>.<extension>` next to it.
+    fn output_path(&self, input_path: &Path) -> Result<PathBuf> {
+        output_path_for(input_path, self.extension())
+    }
+
+    /// Render and write the output file, returning its path.
+    fn save(
+        &self,
+        response: &TranscriptionResponse,
+        job_info: &JobResponse,
+        input_path: &Path,
+    ) -> Result<PathBuf> {
+        let output_path = self.output_path(input_path)?;
+        let content = self.format(response, job_info, input_path)?;
+        std::fs::write(&output_path, content)
+            .with_context(|| format!("Failed to write {}", output_path.display()))?;
+        Ok(output_path)
+    }
+}
+
+/// The set of formatters available by name.
+pub struct FormatterRegistry {
+    formatters: Vec<Box<dyn OutputFormatter>>,
+}
+
+impl FormatterRegistry {
+    /// An empty registry.
+    pub fn new() -> Self {
+        Self { formatters: Vec::new() }
+    }
+
+    /// A registry with the built-in md, txt, json, srt and vtt formatters.
+    pub fn with_builtins() -> Self {
+        let mut registry = Self::new();
+        registry.register(MarkdownFormatter);
+        registry.register(TextFormatter);
+        registry.register(JsonFormatter);
+        registry.register(SrtFormatter);
+        registry.register(VttFormatter::default());
+        registry
+    }
+
+    /// Add a formatter, replacing any existing one with the same name.
+    pub fn register(&mut self, formatter: impl OutputFormatter + 'static) {
+        let formatter: Box<dyn OutputFormatter> = Box::new(formatter);
+        match self.formatters.iter().position(|f| f.name() == formatter.name()) {
+            Some(index) => self.formatters[index] = formatter,
+            None => self.formatters.push(formatter),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn OutputFormatter> {
+        self.formatters
+            .iter()
+            .find(|f| f.name().eq_ignore_ascii_case(name))
+            .map(|f| f.as_ref())
+    }
+
+    /// Names of the registered formatters, in registration order.
+    pub fn names(&self) -> Vec<&str> {
+        self.formatters.iter().map(|f| f.name()).collect()
+    }
+
+    /// Look up every name in `names`, failing on the first unknown one.
+    pub fn select(&self, names: &[String]) -> Result<Vec<&dyn OutputFormatter>> {
+        names
+            .iter()
+            .map(|name| {
+                self.get(name).with_context(|| {
+                    format!(
+                        "Unknown output format '{}' (available: {})",
+                        name,
+                        self.names().join(", ")
+                    )
+                })
+            })
+            .collect()
+    }
+}
+
+impl Default for FormatterRegistry {
+    fn default() -> Self {
+        Self::with_builtins()
+    }
+}
+
+/// Path of the output file for `input_path` with the given extension, next to the input.
+fn output_path_for(input_path: &Path, extension: &str) -> Result<PathBuf> {
+    let parent = input_path.parent().unwrap_or_else(|| Path::new(""));
+    let stem = input_path
+        .file_stem()
+        .context("Invalid file name")?
+        .to_str()
+        .context("Invalid file name encoding")?;
+
+    Ok(parent.join(format!("{}.{}", stem, extension)))
+}
+
+fn format_datetime(timestamp: f64) -> String {
+    chrono::DateTime::from_timestamp(timestamp as i64, 0)
+        .map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_else(|| "Unknown".to_string())
+}
+
+/// Markdown with the transcript followed by audio file information.
+pub struct MarkdownFormatter;
+
+impl OutputFormatter for MarkdownFormatter {
+    fn name(&self) -> &str {
+        "md"
+    }
+
+    fn extension(&self) -> &str {
+        "md"
+    }
+
+    fn format(
+        &self,
+        response: &TranscriptionResponse,
+        job_info: &JobResponse,
+        input_path: &Path,
+    ) -> Result<String> {
+        // Calculate total duration from last segment
+        let duration = response.segments.last()
+            .map(|seg| seg.end)
+            .unwrap_or(0.0);
+
+        // Format duration as minutes:seconds
+        let minutes = (duration / 60.0).floor();
+        let seconds = (duration % 60.0).round();
+
+        // Build markdown content
+        let mut markdown = String::new();
+
+        // Add transcription text
+        markdown.push_str(&response.text);
+        markdown.push_str("\n\n---\n\n");
+
+        // Add file information section
+        let source_file = input_path.file_name().context("Invalid file name")?;
+        markdown.push_str("## Audio File Information\n\n");
+        markdown.push_str(&format!("- **Source File:** {}\n", source_file.to_string_lossy()));
+        if let Some(file_info) = &job_info.file_info {
+            markdown.push_str(&format!("- **File Size:** {} bytes\n", file_info.size));
+        }
+        markdown.push_str(&format!("- **Duration:** {}:{:02}\n", minutes, seconds));
+        if let Some(created_at) = job_info.created_at {
+            markdown.push_str(&format!("- **Transcribed:** {}\n", format_datetime(created_at)));
+        }
+
+        Ok(markdown)
+    }
+}
+
+/// The bare transcript text.
+pub struct TextFormatter;
+
+impl OutputFormatter for TextFormatter {
+    fn name(&self) -> &str {
+        "txt"
+    }
+
+    fn extension(&self) -> &str {
+        "txt"
+    }
+
+    fn format(&self, response: &TranscriptionResponse, _: &JobResponse, _: &Path) -> Result<String> {
+        Ok(format!("{}\n", response.text.trim()))
+    }
+}
+
+/// The full transcription response, with every segment field, plus the job envelope.
+///
+/// The output deserializes back into a [`TranscriptRecord`].
+pub struct JsonFormatter;
+
+impl OutputFormatter for JsonFormatter {
+    fn name(&self) -> &str {
+        "json"
+    }
+
+    fn extension(&self) -> &str {
+        "json"
+    }
+
+    fn format(&self, response: &TranscriptionResponse, job_info: &JobResponse, _: &Path) -> Result<String> {
+        let record = TranscriptRecord::new(response.clone(), job_info);
+        Ok(serde_json::to_string_pretty(&record)?)
+    }
+}
+
+/// SubRip subtitles.
+pub struct SrtFormatter;
+
+impl OutputFormatter for SrtFormatter {
+    fn name(&self) -> &str {
+        "srt"
+    }
+
+    fn extension(&self) -> &str {
+        "srt"
+    }
+
+    fn format(&self, response: &TranscriptionResponse, _: &JobResponse, _: &Path) -> Result<String> {
+        Ok(format_srt(response))
+    }
+}
+
+/// WebVTT subtitles with the job metadata in a leading NOTE block.
+#[derive(Debug, Clone, Default)]
+pub struct VttFormatter {
+    /// Cue settings appended to every cue timing line, e.g. `line:90% align:center`.
+    pub cue_settings: Option<String>,
+}
+
+impl VttFormatter {
+    pub fn with_cue_settings(cue_settings: impl Into<String>) -> Self {
+        Self { cue_settings: Some(cue_settings.into()) }
+    }
+}
+
+impl OutputFormatter for VttFormatter {
+    fn name(&self) -> &str {
+        "vtt"
+    }
+
+    fn extension(&self) -> &str {
+        "vtt"
+    }
+
+    fn format(
+        &self,
+        response: &TranscriptionResponse,
+        job_info: &JobResponse,
+        input_path: &Path,
+    ) -> Result<String> {
+        let source_file = input_path
+            .file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default();
+        Ok(format_vtt(response, job_info, &source_file, self.cue_settings.as_deref()))
+    }
+}
+
+fn format_timestamp(seconds: f64, ms_separator: char) -> String {
+    let total_ms = (seconds.max(0.0) * 1000.0).round() as u64;
+    let (hours, rest) = (total_ms / 3_600_000, total_ms % 3_600_000);
+    let (minutes, rest) = (rest / 60_000, rest % 60_000);
+    let (secs, ms) = (rest / 1000, rest % 1000);
+    format!("{:02}:{:02}:{:02}{}{:03}", hours, minutes, secs, ms_separator, ms)
+}
+
+/// Format seconds as an SRT timestamp (`HH:MM:SS,mmm`).
+pub fn format_srt_timestamp(seconds: f64) -> String {
+    format_timestamp(seconds, ',')
+}
+
+/// Format seconds as a WebVTT timestamp (`HH:MM:SS.mmm`).
+pub fn format_vtt_timestamp(seconds: f64) -> String {
+    format_timestamp(seconds, '.')
+}
+
+/// Segments that produce a cue, with Whisper's surrounding whitespace trimmed.
+fn cues(response: &TranscriptionResponse) -> impl Iterator<Item = (&Segment, &str)> {
+    response
+        .segments
+        .iter()
+        .map(|seg| (seg, seg.text.trim()))
+        .filter(|(_, text)| !text.is_empty())
+}
+
+/// Render segments as SRT cues, skipping segments with no text.
+pub fn format_srt(response: &TranscriptionResponse) -> String {
+    let mut srt = String::new();
+
+    for (index, (segment, text)) in cues(response).enumerate() {
+        if index > 0 {
+            srt.push('\n');
+        }
+        srt.push_str(&format!(
+            "{}\n{} --> {}\n{}\n",
+            index + 1,
+            format_srt_timestamp(segment.start),
+            format_srt_timestamp(segment.end),
+            text
+        ));
+    }
+
+    srt
+}
+
+/// Render segments as a WebVTT file with the job metadata in a leading NOTE block.
+pub fn format_vtt(
+    response: &TranscriptionResponse,
+    job_info: &JobResponse,
+    source_file: &str,
+    cue_settings: Option<&str>,
+) -> String {
+    let mut vtt = String::from("WEBVTT\n\n");
+
+    // "-->" is not allowed inside a NOTE block
+    let note_line = |label: &str, value: &str| format!("{}: {}\n", label, value.replace("-->", "->"));
+    vtt.push_str("NOTE\n");
+    vtt.push_str(&note_line("Job ID", &job_info.job_id));
+    vtt.push_str(&note_line("Source File", job_info.filename.as_deref().unwrap_or(source_file)));
+    if let Some(created_at) = job_info.created_at {
+        vtt.push_str(&note_line("Created", &format_datetime(created_at)));
+    }
+
+    let settings = cue_settings
+        .map(str::trim)
+        .filter(|settings| !settings.is_empty())
+        .map(|settings| format!(" {}", settings))
+        .unwrap_or_default();
+
+    for (index, (segment, text)) in cues(response).enumerate() {
+        vtt.push_str(&format!(
+            "\n{}\n{} --> {}{}\n{}\n",
+            index + 1,
+            format_vtt_timestamp(segment.start),
+            format_vtt_timestamp(segment.end),
+            settings,
+            text
+        ));
+    }
+
+    vtt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::JobStatus;
+    use tempfile::tempdir;
+
+    fn fixture() -> TranscriptionResponse {
+        serde_json::from_str(include_str!("../tests/fixtures/test.json")).unwrap()
+    }
+
+    fn job_info() -> JobResponse {
+        JobResponse {
+            job_id: String::from("job-1"),
+            status: JobStatus::Completed,
+            message: String::new(),
+            result: None,
+            file_info: None,
+            created_at: Some(1234567890.0),
+            filename: Some(String::from("talk.wav")),
+            progress: None,
+        }
+    }
+
+    #[test]
+    fn test_format_srt_timestamp() {
+        assert_eq!(format_srt_timestamp(0.0), "00:00:00,000");
+        assert_eq!(format_srt_timestamp(2.72), "00:00:02,720");
+        assert_eq!(format_srt_timestamp(3661.0005), "01:01:01,001");
+        assert_eq!(format_srt_timestamp(-1.0), "00:00:00,000");
+    }
+
+    #[test]
+    fn test_format_srt() {
+        let srt = format_srt(&fixture());
+        assert_eq!(
+            srt,
+            "1\n00:00:00,000 --> 00:00:02,720\nThis is a test of the whisper transcription service.\n\n\
+             2\n00:00:02,720 --> 00:00:05,440\nThe quick brown fox dumps over the lazy dog.\n"
+        );
+    }
+
+    #[test]
+    fn test_format_vtt() {
+        let vtt = format_vtt(&fixture(), &job_info(), "ignored.wav", Some("line:90%"));
+        assert_eq!(
+            vtt,
+            "WEBVTT\n\n\
+             NOTE\nJob ID: job-1\nSource File: talk.wav\nCreated: 2009-02-13 23:31:30\n\n\
+             1\n00:00:00.000 --> 00:00:02.720 line:90%\nThis is a test of the whisper transcription service.\n\n\
+             2\n00:00:02.720 --> 00:00:05.440 line:90%\nThe quick brown fox dumps over the lazy dog.\n"
+        );
+
+        let vtt = format_vtt(&fixture(), &job_info(), "ignored.wav", None);
+        assert!(vtt.contains("00:00:00.000 --> 00:00:02.720\n"));
+    }
+
+    #[test]
+    fn test_registry_saves_multiple_formats() {
+        let temp_dir = tempdir().unwrap();
+        let input_path = temp_dir.path().join("talk.wav");
+        let registry = FormatterRegistry::with_builtins();
+
+        let names: Vec<String> = ["md", "txt", "json", "srt", "vtt"].iter().map(|s| s.to_string()).collect();
+        for formatter in registry.select(&names).unwrap() {
+            let path = formatter.save(&fixture(), &job_info(), &input_path).unwrap();
+            assert_eq!(path, temp_dir.path().join(format!("talk.{}", formatter.extension())));
+            assert!(path.exists());
+        }
+
+        assert!(registry.select(&["docx".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_register_custom_formatter() {
+        struct Shouting;
+
+        impl OutputFormatter for Shouting {
+            fn name(&self) -> &str {
+                "shout"
+            }
+
+            fn extension(&self) -> &str {
+                "txt"
+            }
+
+            fn format(&self, response: &TranscriptionResponse, _: &JobResponse, _: &Path) -> Result<String> {
+                Ok(response.text.to_uppercase())
+            }
+        }
+
+        let mut registry = FormatterRegistry::with_builtins();
+        registry.register(Shouting);
+        registry.register(VttFormatter::with_cue_settings("align:start"));
+
+        assert_eq!(registry.names(), vec!["md", "txt", "json", "srt", "vtt", "shout"]);
+        let shout = registry.get("shout").unwrap();
+        let text = shout.format(&fixture(), &job_info(), Path::new("talk.wav")).unwrap();
+        assert!(text.contains("QUICK BROWN FOX"));
+
+        let vtt = registry.get("vtt").unwrap().format(&fixture(), &job_info(), Path::new("talk.wav")).unwrap();
+        assert!(vtt.contains(" align:start\n"));
+    }
+}
@@ -0,0 +1,42 @@
+use std::path::PathBuf;
+
+/// Name of the client's directory inside each platform directory.
+const APP_DIR: &str = "whisper-client";
+
+/// Where the config file lives: `$XDG_CONFIG_HOME/whisper-client` (by default
+/// `~/.config/whisper-client`) on Linux, `~/Library/Application Support/whisper-client`
+/// on macOS and `%APPDATA%\whisper-client` on Windows.
+pub fn config_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join(APP_DIR))
+}
+
+/// Where data that can be rebuilt lives, such as the result cache: `$XDG_CACHE_HOME/whisper-client`
+/// (by default `~/.cache/whisper-client`) on Linux, `~/Library/Caches/whisper-client` on macOS
+/// and `%LOCALAPPDATA%\whisper-client` on Windows.
+pub fn cache_dir() -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join(APP_DIR))
+}
+
+/// Where state kept between runs lives, such as batch manifests: `$XDG_STATE_HOME/whisper-client`
+/// (by default `~/.local/state/whisper-client`) on Linux. Other platforms have no separate
+/// state directory, so the local data directory is used.
+pub fn state_dir() -> Option<PathBuf> {
+    dirs::state_dir().or_else(dirs::data_local_dir).map(|dir| dir.join(APP_DIR))
+}
+
+/// Where the config file used to live on every platform, regardless of `XDG_CONFIG_HOME`.
+pub(crate) fn legacy_config_dir() -> Option<PathBuf> {
+    dirs::home_dir().map(|dir| dir.join(".config").join(APP_DIR))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dirs_are_app_specific() {
+        for dir in [config_dir(), cache_dir(), state_dir(), legacy_config_dir()].into_iter().flatten() {
+            assert!(dir.ends_with(APP_DIR));
+        }
+    }
+}
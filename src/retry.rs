@@ -0,0 +1,160 @@
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::time::Duration;
+use crate::error::WhisperError;
+
+/// Statuses that mean the service turned a request away without acting on it.
+const REJECTED_STATUSES: [StatusCode; 2] = [StatusCode::TOO_MANY_REQUESTS, StatusCode::SERVICE_UNAVAILABLE];
+
+/// When and how often to repeat a request that failed for a transient reason.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// Total attempts per request, including the first. 1 disables retries.
+    pub max_attempts: u32,
+    /// Delay cap before the first retry; it doubles for every retry after that.
+    pub base_delay_ms: u64,
+    /// Upper bound for any single delay.
+    pub max_delay_ms: u64,
+    /// HTTP statuses worth trying again.
+    pub retry_statuses: Vec<u16>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay_ms: 500,
+            max_delay_ms: 10_000,
+            retry_statuses: vec![408, 429, 500, 502, 503, 504],
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that tries every request once.
+    pub fn none() -> Self {
+        Self { max_attempts: 1, ..Self::default() }
+    }
+
+    /// How long to wait before retry number `retry` (starting at 0).
+    ///
+    /// Uses "full jitter": a random delay up to the exponential backoff, so
+    /// clients that failed together don't all retry together.
+    pub fn delay(&self, retry: u32) -> Duration {
+        let backoff = self
+            .base_delay_ms
+            .saturating_mul(1u64.checked_shl(retry).unwrap_or(u64::MAX))
+            .min(self.max_delay_ms);
+        Duration::from_millis(fastrand::u64(0..=backoff))
+    }
+
+    /// Whether a request that failed with `error` should be sent again.
+    ///
+    /// Requests that aren't `idempotent` (uploads) are only repeated when the
+    /// service can't have acted on them: the connection was never made, or the
+    /// service turned them away with 429 or 503 (if those are retryable). A timeout,
+    /// a dropped connection or an error such as 500 after the upload may have created
+    /// a job already, so those aren't retried.
+    pub fn should_retry(&self, error: &WhisperError, idempotent: bool) -> bool {
+        match error {
+            WhisperError::Connection { source, .. } => idempotent || source.is_connect(),
+            WhisperError::Timeout { .. } => idempotent,
+            WhisperError::Http { status, .. } => {
+                self.is_retryable_status(*status) && (idempotent || REJECTED_STATUSES.contains(status))
+            }
+            _ => false,
+        }
+    }
+
+    pub fn is_retryable_status(&self, status: StatusCode) -> bool {
+        self.retry_statuses.contains(&status.as_u16())
+    }
+
+    /// Run `call` until it succeeds, fails for good, or runs out of attempts.
+    pub async fn run<T, F, Fut>(&self, idempotent: bool, mut call: F) -> Result<T, WhisperError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, WhisperError>>,
+    {
+        let mut retry = 0;
+        loop {
+            match call().await {
+                Err(e) if retry + 1 < self.max_attempts && self.should_retry(&e, idempotent) => {
+                    tokio::time::sleep(self.delay(retry)).await;
+                    retry += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn http_error(status: u16) -> WhisperError {
+        WhisperError::Http { status: StatusCode::from_u16(status).unwrap(), body: String::new() }
+    }
+
+    #[test]
+    fn test_delay_is_capped() {
+        let policy = RetryPolicy { base_delay_ms: 100, max_delay_ms: 1000, ..RetryPolicy::default() };
+        for retry in 0..40 {
+            let cap = (100u64 << retry.min(20)).min(1000);
+            assert!(policy.delay(retry) <= Duration::from_millis(cap));
+        }
+    }
+
+    #[test]
+    fn test_should_retry() {
+        let policy = RetryPolicy::default();
+        let timeout = WhisperError::Timeout { url: "http://localhost".to_string() };
+        assert!(policy.should_retry(&http_error(503), true));
+        assert!(policy.should_retry(&http_error(503), false));
+        assert!(policy.should_retry(&http_error(429), false));
+        assert!(policy.should_retry(&http_error(500), true));
+        assert!(!policy.should_retry(&http_error(500), false));
+        assert!(!policy.should_retry(&http_error(502), false));
+        assert!(!policy.should_retry(&http_error(400), true));
+        assert!(!policy.should_retry(&http_error(404), true));
+        assert!(policy.should_retry(&timeout, true));
+        assert!(!policy.should_retry(&timeout, false));
+        assert!(!policy.should_retry(&WhisperError::JobNotFound("job".to_string()), true));
+    }
+
+    #[tokio::test]
+    async fn test_run_retries_until_success_or_limit() {
+        let policy = RetryPolicy { base_delay_ms: 1, max_delay_ms: 1, ..RetryPolicy::default() };
+
+        let calls = AtomicU32::new(0);
+        let result = policy.run(true, || async {
+            match calls.fetch_add(1, Ordering::SeqCst) {
+                0 => Err(http_error(502)),
+                _ => Ok("done"),
+            }
+        }).await;
+        assert_eq!(result.unwrap(), "done");
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        let calls = AtomicU32::new(0);
+        let result: Result<(), _> = policy.run(true, || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Err(http_error(503))
+        }).await;
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        // Errors that won't go away are returned straight away
+        let calls = AtomicU32::new(0);
+        let result: Result<(), _> = policy.run(true, || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Err(http_error(400))
+        }).await;
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,104 @@
+use reqwest::{Certificate, ClientBuilder, Identity};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use crate::auth::Secret;
+use crate::error::{Result, WhisperError};
+
+/// TLS settings for talking to a service behind a private CA or requiring client certificates.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TlsConfig {
+    /// PEM file with CA certificates to trust in addition to the system ones.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ca_bundle: Option<PathBuf>,
+    /// Client certificate: a PEM certificate (with `client_key`), or else a PKCS#12 archive.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_cert: Option<PathBuf>,
+    /// PKCS#8 PEM private key for a PEM `client_cert`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_key: Option<PathBuf>,
+    /// Password of a PKCS#12 `client_cert`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_cert_password: Option<Secret>,
+    /// Skip certificate verification entirely. Only for local testing.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub danger_accept_invalid_certs: bool,
+}
+
+impl TlsConfig {
+    /// Trust the CA bundle, present the client certificate and, if asked, skip verification.
+    pub(crate) fn apply(&self, mut builder: ClientBuilder) -> Result<ClientBuilder> {
+        if let Some(path) = &self.ca_bundle {
+            let certificates = Certificate::from_pem_bundle(&read(path)?)
+                .map_err(|e| invalid(path, e))?;
+            if certificates.is_empty() {
+                return Err(WhisperError::Config(format!("No certificates found in {}", path.display())));
+            }
+            for certificate in certificates {
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+
+        if let Some(cert_path) = &self.client_cert {
+            let identity = match &self.client_key {
+                Some(key_path) => Identity::from_pkcs8_pem(&read(cert_path)?, &read(key_path)?)
+                    .map_err(|e| invalid(cert_path, e))?,
+                None => {
+                    let password = match &self.client_cert_password {
+                        Some(password) => password.resolve()?,
+                        None => String::new(),
+                    };
+                    Identity::from_pkcs12_der(&read(cert_path)?, &password)
+                        .map_err(|e| invalid(cert_path, e))?
+                }
+            };
+            builder = builder.identity(identity);
+        } else if self.client_key.is_some() {
+            return Err(WhisperError::Config("tls.client_key is set without tls.client_cert".to_string()));
+        }
+
+        if self.danger_accept_invalid_certs {
+            builder = builder.danger_accept_invalid_certs(true);
+        }
+        Ok(builder)
+    }
+}
+
+fn read(path: &Path) -> Result<Vec<u8>> {
+    std::fs::read(path).map_err(|e| WhisperError::io(path, e))
+}
+
+fn invalid(path: &Path, e: reqwest::Error) -> WhisperError {
+    WhisperError::Config(format!("Invalid certificate or key in {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_default_applies_cleanly() {
+        let builder = TlsConfig::default().apply(reqwest::Client::builder()).unwrap();
+        assert!(builder.build().is_ok());
+    }
+
+    #[test]
+    fn test_bad_files_are_config_errors() {
+        let temp_dir = tempdir().unwrap();
+        let garbage = temp_dir.path().join("garbage.pem");
+        std::fs::write(&garbage, "not a certificate").unwrap();
+
+        let config = TlsConfig { ca_bundle: Some(temp_dir.path().join("missing.pem")), ..Default::default() };
+        assert!(matches!(config.apply(reqwest::Client::builder()), Err(WhisperError::Io { .. })));
+
+        let config = TlsConfig { ca_bundle: Some(garbage.clone()), ..Default::default() };
+        assert!(matches!(config.apply(reqwest::Client::builder()), Err(WhisperError::Config(_))));
+
+        let config = TlsConfig { client_cert: Some(garbage.clone()), ..Default::default() };
+        assert!(matches!(config.apply(reqwest::Client::builder()), Err(WhisperError::Config(_))));
+
+        let config = TlsConfig { client_key: Some(garbage), ..Default::default() };
+        assert!(matches!(config.apply(reqwest::Client::builder()), Err(WhisperError::Config(_))));
+    }
+}
@@ -1,12 +1,13 @@
-use anyhow::{Context, Result};
-use std::path::PathBuf;
+use anyhow::Result;
+use std::path::{Path, PathBuf};
 use crate::models::{TranscriptionResponse, JobResponse};
+use crate::output::{MarkdownFormatter, OutputFormatter};
 
 pub fn get_supported_formats() -> Vec<&'static str> {
     vec!["mp3", "wav", "m4a", "ogg", "flac", "mkv", "mp4"]
 }
 
-pub fn is_supported_audio_format(path: &PathBuf) -> bool {
+pub fn is_supported_audio_format(path: &Path) -> bool {
     let supported = get_supported_formats();
     path.extension()
         .and_then(|ext| ext.to_str())
@@ -37,53 +38,41 @@ pub fn collect_audio_files(path: &PathBuf, recursive: bool) -> Result<Vec<PathBu
     Ok(files)
 }
 
+/// When to leave an input alone because its outputs are already there.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SkipPolicy {
+    /// Always transcribe.
+    #[default]
+    Never,
+    /// Skip when every output file exists.
+    Existing,
+    /// Skip when every output file exists and is newer than the input.
+    IfNewer,
+}
+
+/// Whether `input` can be skipped under `policy`, given the outputs it would produce.
+pub fn should_skip(input: &Path, outputs: &[PathBuf], policy: SkipPolicy) -> bool {
+    let modified = |path: &Path| std::fs::metadata(path).and_then(|m| m.modified()).ok();
+
+    match policy {
+        SkipPolicy::Never => false,
+        _ if outputs.is_empty() => false,
+        SkipPolicy::Existing => outputs.iter().all(|output| output.is_file()),
+        SkipPolicy::IfNewer => match modified(input) {
+            Some(input_modified) => outputs
+                .iter()
+                .all(|output| modified(output).is_some_and(|m| m >= input_modified)),
+            None => false,
+        },
+    }
+}
+
 pub fn save_markdown_response(
     response: &TranscriptionResponse,
-    input_path: &PathBuf,
+    input_path: &Path,
     job_info: &JobResponse,
 ) -> Result<PathBuf> {
-    let parent = input_path.parent().unwrap_or_else(|| std::path::Path::new(""));
-    let stem = input_path
-        .file_stem()
-        .context("Invalid file name")?
-        .to_str()
-        .context("Invalid file name encoding")?;
-    
-    let output_path = parent.join(format!("{}.md", stem));
-    
-    // Calculate total duration from last segment
-    let duration = response.segments.last()
-        .map(|seg| seg.end)
-        .unwrap_or(0.0);
-    
-    // Format duration as minutes:seconds
-    let minutes = (duration / 60.0).floor();
-    let seconds = (duration % 60.0).round();
-    
-    // Build markdown content
-    let mut markdown = String::new();
-    
-    // Add transcription text
-    markdown.push_str(&response.text);
-    markdown.push_str("\n\n---\n\n");
-    
-    // Add file information section
-    markdown.push_str("## Audio File Information\n\n");
-    markdown.push_str(&format!("- **Source File:** {}\n", input_path.file_name().unwrap().to_string_lossy()));
-    if let Some(file_info) = &job_info.file_info {
-        markdown.push_str(&format!("- **File Size:** {} bytes\n", file_info.size));
-    }
-    markdown.push_str(&format!("- **Duration:** {}:{:02}\n", minutes, seconds));
-    if let Some(created_at) = job_info.created_at {
-        let datetime = chrono::DateTime::from_timestamp(created_at as i64, 0)
-            .map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
-            .unwrap_or_else(|| "Unknown".to_string());
-        markdown.push_str(&format!("- **Transcribed:** {}\n", datetime));
-    }
-    
-    std::fs::write(&output_path, markdown)?;
-    
-    Ok(output_path)
+    MarkdownFormatter.save(response, job_info, input_path)
 }
 
 #[cfg(test)]
@@ -145,9 +134,44 @@ mod tests {
         assert_eq!(files.len(), 1, "Should handle single file");
     }
 
+    #[test]
+    fn test_should_skip() {
+        use std::time::{Duration, SystemTime};
+
+        let temp_dir = tempdir().unwrap();
+        let input = temp_dir.path().join("talk.mp3");
+        let md = temp_dir.path().join("talk.md");
+        let srt = temp_dir.path().join("talk.srt");
+        std::fs::write(&input, "dummy").unwrap();
+        std::fs::write(&md, "dummy").unwrap();
+        let outputs = vec![md.clone(), srt.clone()];
+
+        // Not every output exists yet
+        assert!(!should_skip(&input, &outputs, SkipPolicy::Existing));
+        assert!(!should_skip(&input, &outputs, SkipPolicy::IfNewer));
+
+        std::fs::write(&srt, "dummy").unwrap();
+        assert!(should_skip(&input, &outputs, SkipPolicy::Existing));
+        assert!(!should_skip(&input, &outputs, SkipPolicy::Never));
+
+        // An output older than the input means the input changed since
+        let now = SystemTime::now();
+        let set_modified = |path: &PathBuf, time: SystemTime| {
+            std::fs::File::options().write(true).open(path).unwrap().set_modified(time).unwrap();
+        };
+        set_modified(&input, now);
+        set_modified(&md, now + Duration::from_secs(10));
+        set_modified(&srt, now - Duration::from_secs(10));
+        assert!(should_skip(&input, &outputs, SkipPolicy::Existing));
+        assert!(!should_skip(&input, &outputs, SkipPolicy::IfNewer));
+
+        set_modified(&srt, now + Duration::from_secs(10));
+        assert!(should_skip(&input, &outputs, SkipPolicy::IfNewer));
+    }
+
     #[test]
     fn test_save_markdown_response() {
-        use crate::models::{FileInfo, JobResponse, Segment, TranscriptionResponse};
+        use crate::models::{FileInfo, JobResponse, JobStatus, Segment, TranscriptionResponse};
         
         let temp_dir = tempdir().unwrap();
         let input_path = temp_dir.path().join("test_audio.mp3");
@@ -166,6 +190,7 @@ mod tests {
                     avg_logprob: -0.5,
                     compression_ratio: 1.0,
                     no_speech_prob: 0.1,
+                    extra: Default::default(),
                 },
                 Segment {
                     id: 1,
@@ -178,13 +203,15 @@ mod tests {
                     avg_logprob: -0.5,
                     compression_ratio: 1.0,
                     no_speech_prob: 0.1,
+                    extra: Default::default(),
                 },
             ],
+            extra: Default::default(),
         };
 
         let job_info = JobResponse {
             job_id: String::from("test-job"),
-            status: String::from("completed"),
+            status: JobStatus::Completed,
             message: String::from(""),
             result: Some(response.clone()),
             file_info: Some(FileInfo {
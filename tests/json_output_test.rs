@@ -0,0 +1,50 @@
+use std::path::PathBuf;
+use tempfile::tempdir;
+use whisper_client::{
+    FileInfo, JobResponse, JobStatus, JsonFormatter, OutputFormatter, TranscriptRecord, TranscriptionResponse,
+};
+
+fn fixture_path() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/test.json")
+}
+
+#[test]
+fn test_json_output_preserves_all_fields() {
+    let fixture = std::fs::read_to_string(fixture_path()).unwrap();
+    let mut expected: serde_json::Value = serde_json::from_str(&fixture).unwrap();
+    // Add a field the client doesn't model to make sure it survives too
+    expected["segments"][0]["words"] = serde_json::json!([{"word": "This", "start": 0.0, "end": 0.2}]);
+    let response: TranscriptionResponse = serde_json::from_value(expected.clone()).unwrap();
+
+    let job_info = JobResponse {
+        job_id: String::from("job-1"),
+        status: JobStatus::Completed,
+        message: String::new(),
+        result: Some(response.clone()),
+        file_info: Some(FileInfo {
+            name: String::from("test.wav"),
+            size: 1000,
+        }),
+        created_at: Some(1234567890.0),
+        filename: Some(String::from("test.wav")),
+        progress: None,
+    };
+
+    let temp_dir = tempdir().unwrap();
+    let input_path = temp_dir.path().join("test.wav");
+    let output_path = JsonFormatter.save(&response, &job_info, &input_path).unwrap();
+    assert_eq!(output_path, temp_dir.path().join("test.json"));
+
+    let written: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&output_path).unwrap()).unwrap();
+    assert_eq!(written["transcription"], expected);
+    assert_eq!(written["job"]["job_id"], "job-1");
+    assert_eq!(written["job"]["status"], "completed");
+    assert_eq!(written["job"]["file_info"]["size"], 1000);
+    assert!(written["job"].get("result").is_none(), "result should not be duplicated in the envelope");
+
+    // The file can be read back to re-render without the service
+    let record: TranscriptRecord = serde_json::from_value(written).unwrap();
+    assert_eq!(record.transcription.segments.len(), 2);
+    assert_eq!(record.transcription.segments[0].tokens.len(), 12);
+}